@@ -0,0 +1,191 @@
+//! Compares the per-contact queries contact-list sync used to run against the SYN handler as it is now.
+//!
+//! Needs a migrated, disposable database:
+//! `R2M_BENCH_DATABASE_URL=mysql://... cargo bench --features bench --bench syn`
+//! The number of contacts can be changed with `R2M_BENCH_CONTACTS` (default 5000).
+
+use rusty_retro_messaging::{AuthenticatedUser, Syn, UserCommand};
+use sqlx::mysql::MySqlPoolOptions;
+use sqlx::{MySql, Pool, Row};
+use std::env;
+use std::sync::Arc;
+use std::time::Instant;
+
+const GROUPS: usize = 20;
+const ITERATIONS: usize = 5;
+const PROTOCOL_VERSION: u32 = 12;
+
+#[tokio::main]
+async fn main() -> Result<(), sqlx::Error> {
+    let Ok(database_url) = env::var("R2M_BENCH_DATABASE_URL") else {
+        println!("R2M_BENCH_DATABASE_URL is not set, skipping");
+        return Ok(());
+    };
+
+    let contacts = env::var("R2M_BENCH_CONTACTS")
+        .ok()
+        .and_then(|contacts| contacts.parse().ok())
+        .unwrap_or(5000);
+
+    let pool = MySqlPoolOptions::new()
+        .max_connections(1)
+        .connect(&database_url)
+        .await?;
+
+    let prefix = format!("bench-{}", chrono::Utc::now().timestamp_millis());
+    println!("Seeding {contacts} contacts in {GROUPS} groups...");
+    let (user_id, email) = seed(&pool, &prefix, contacts).await?;
+
+    let syn = Syn::new(pool.clone());
+    let mut results = Vec::new();
+    for (name, baseline) in [("per-contact", true), ("SYN", false)] {
+        let mut timings = Vec::with_capacity(ITERATIONS);
+        let mut lines = 0;
+        for _ in 0..ITERATIONS {
+            // A fresh user and timestamps the client can't have cached, so the whole list is sent every time
+            let mut user = AuthenticatedUser::new(Arc::new(email.clone()));
+            let mut version_number = 0;
+
+            let start = Instant::now();
+            lines = if baseline {
+                per_contact(&pool, user_id).await?
+            } else {
+                syn.handle(
+                    PROTOCOL_VERSION,
+                    "SYN 1 0 0\r\n",
+                    &mut user,
+                    &mut version_number,
+                )
+                .await
+                .expect("SYN failed")
+                .iter()
+                .filter(|response| response.starts_with("LST "))
+                .count()
+            };
+
+            timings.push(start.elapsed());
+        }
+
+        timings.sort();
+        results.push((name, timings[ITERATIONS / 2], lines));
+    }
+
+    for (name, median, lines) in &results {
+        println!("{name:>12}: {median:>10.2?} median over {ITERATIONS} runs ({lines} LST lines)");
+    }
+
+    sqlx::query("DELETE FROM users WHERE email LIKE ?")
+        .bind(format!("{prefix}%"))
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+async fn seed(
+    pool: &Pool<MySql>,
+    prefix: &str,
+    contacts: usize,
+) -> Result<(i32, String), sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    let mut user_ids = Vec::with_capacity(contacts + 1);
+    for index in 0..=contacts {
+        let result = sqlx::query(
+            "INSERT INTO users (email, password, display_name, puid, guid, gtc, blp, verified)
+            VALUES (?, '', ?, 0, ?, 'A', 'AL', TRUE)",
+        )
+        .bind(format!("{prefix}-{index}@bench.invalid"))
+        .bind(format!("{prefix}-{index}"))
+        .bind(guid_create::GUID::rand().to_string().to_lowercase())
+        .execute(&mut *transaction)
+        .await?;
+
+        user_ids.push(result.last_insert_id() as i32);
+    }
+
+    let user_id = user_ids[0];
+    let mut group_ids = Vec::with_capacity(GROUPS);
+    for index in 0..GROUPS {
+        let result = sqlx::query("INSERT INTO groups (user_id, name, guid) VALUES (?, ?, ?)")
+            .bind(user_id)
+            .bind(format!("Group{index}"))
+            .bind(guid_create::GUID::rand().to_string().to_lowercase())
+            .execute(&mut *transaction)
+            .await?;
+
+        group_ids.push(result.last_insert_id() as i32);
+    }
+
+    for (index, contact_id) in user_ids[1..].iter().enumerate() {
+        let result = sqlx::query(
+            "INSERT INTO contacts (user_id, contact_id, display_name, in_forward_list, in_allow_list, in_block_list)
+            VALUES (?, ?, ?, TRUE, TRUE, FALSE)",
+        )
+        .bind(user_id)
+        .bind(contact_id)
+        .bind(format!("{prefix}-{}", index + 1))
+        .execute(&mut *transaction)
+        .await?;
+
+        sqlx::query("INSERT INTO group_members (group_id, contact_id) VALUES (?, ?)")
+            .bind(group_ids[index % GROUPS])
+            .bind(result.last_insert_id() as i32)
+            .execute(&mut *transaction)
+            .await?;
+
+        // Half of the contacts have the user on their forward list as well
+        if index % 2 == 0 {
+            sqlx::query(
+                "INSERT INTO contacts (user_id, contact_id, display_name, in_forward_list, in_allow_list, in_block_list)
+                VALUES (?, ?, ?, TRUE, TRUE, FALSE)",
+            )
+            .bind(contact_id)
+            .bind(user_id)
+            .bind(format!("{prefix}-0"))
+            .execute(&mut *transaction)
+            .await?;
+        }
+    }
+
+    transaction.commit().await?;
+    Ok((user_id, format!("{prefix}-0@bench.invalid")))
+}
+
+/// The queries contact-list sync ran before: one reverse-list lookup per contact and one membership lookup per contact and group
+async fn per_contact(pool: &Pool<MySql>, user_id: i32) -> Result<usize, sqlx::Error> {
+    let groups = sqlx::query("SELECT id FROM groups WHERE user_id = ?")
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+    let contacts = sqlx::query(
+        "SELECT contacts.id, contact_id FROM contacts INNER JOIN users ON contacts.contact_id = users.id
+        WHERE user_id = ?",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    for contact in &contacts {
+        let id: i32 = contact.get("id");
+        let contact_id: i32 = contact.get("contact_id");
+        sqlx::query(
+            "SELECT id FROM contacts WHERE user_id = ? AND contact_id = ? AND in_forward_list = TRUE",
+        )
+        .bind(contact_id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+
+        for group in &groups {
+            let group_id: i32 = group.get("id");
+            sqlx::query("SELECT id FROM group_members WHERE group_id = ? AND contact_id = ?")
+                .bind(group_id)
+                .bind(id)
+                .fetch_optional(pool)
+                .await?;
+        }
+    }
+
+    Ok(contacts.len())
+}
@@ -0,0 +1 @@
+DROP TABLE failed_logins;
\ No newline at end of file
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS failed_logins (
+   id INTEGER AUTO_INCREMENT PRIMARY KEY,
+   email VARCHAR(100) NOT NULL,
+   ip_address VARCHAR(45) NOT NULL,
+   endpoint VARCHAR(20) NOT NULL,
+   attempted_at DATETIME NOT NULL
+);
\ No newline at end of file
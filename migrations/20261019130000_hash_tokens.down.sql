@@ -0,0 +1,9 @@
+DELETE FROM tokens;
+
+ALTER TABLE tokens
+   DROP INDEX tokens_token_hash,
+   DROP COLUMN token_hash,
+   DROP COLUMN created_at,
+   DROP COLUMN source,
+   DROP COLUMN client_type,
+   ADD COLUMN token VARCHAR(150) NOT NULL;
\ No newline at end of file
@@ -0,0 +1,10 @@
+-- Existing tokens are stored in plain text and can't be hashed by the database, so everyone logs in again
+DELETE FROM tokens;
+
+ALTER TABLE tokens
+   DROP COLUMN token,
+   ADD COLUMN token_hash VARCHAR(64) NOT NULL,
+   ADD COLUMN created_at DATETIME NOT NULL,
+   ADD COLUMN source VARCHAR(45) NOT NULL,
+   ADD COLUMN client_type VARCHAR(20) NOT NULL,
+   ADD INDEX tokens_token_hash (token_hash);
\ No newline at end of file
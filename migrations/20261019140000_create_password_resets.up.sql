@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS password_resets (
+   id INTEGER AUTO_INCREMENT PRIMARY KEY,
+   token_hash VARCHAR(64) NOT NULL,
+   valid_until DATETIME NOT NULL,
+   user_id INTEGER NOT NULL REFERENCES users(id),
+   INDEX password_resets_token_hash (token_hash)
+);
\ No newline at end of file
@@ -0,0 +1,4 @@
+DROP TABLE email_confirmations;
+
+ALTER TABLE users
+   DROP COLUMN verified;
\ No newline at end of file
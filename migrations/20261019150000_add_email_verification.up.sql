@@ -0,0 +1,14 @@
+-- Accounts that existed before verification was introduced are trusted
+ALTER TABLE users
+   ADD COLUMN verified BOOLEAN NOT NULL DEFAULT FALSE;
+
+UPDATE users SET verified = TRUE;
+
+CREATE TABLE IF NOT EXISTS email_confirmations (
+   id INTEGER AUTO_INCREMENT PRIMARY KEY,
+   token_hash VARCHAR(64) NOT NULL,
+   email VARCHAR(100) NOT NULL,
+   valid_until DATETIME NOT NULL,
+   user_id INTEGER NOT NULL REFERENCES users(id),
+   INDEX email_confirmations_token_hash (token_hash)
+);
\ No newline at end of file
@@ -0,0 +1,18 @@
+ALTER TABLE users
+   ADD COLUMN totp_secret VARCHAR(64),
+   ADD COLUMN totp_enabled BOOLEAN NOT NULL DEFAULT FALSE,
+   ADD COLUMN totp_last_step BIGINT;
+
+CREATE TABLE IF NOT EXISTS recovery_codes (
+   id INTEGER AUTO_INCREMENT PRIMARY KEY,
+   code_hash VARCHAR(64) NOT NULL,
+   user_id INTEGER NOT NULL REFERENCES users(id)
+);
+
+CREATE TABLE IF NOT EXISTS two_factor_challenges (
+   id INTEGER AUTO_INCREMENT PRIMARY KEY,
+   token_hash VARCHAR(64) NOT NULL,
+   valid_until DATETIME NOT NULL,
+   user_id INTEGER NOT NULL REFERENCES users(id),
+   INDEX two_factor_challenges_token_hash (token_hash)
+);
\ No newline at end of file
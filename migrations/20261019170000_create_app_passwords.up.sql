@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS app_passwords (
+   id INTEGER AUTO_INCREMENT PRIMARY KEY,
+   label VARCHAR(100) NOT NULL,
+   password VARCHAR(100) NOT NULL,
+   created_at DATETIME NOT NULL,
+   last_used_at DATETIME,
+   user_id INTEGER NOT NULL REFERENCES users(id)
+);
\ No newline at end of file
@@ -0,0 +1,9 @@
+DROP TABLE code_redemptions;
+
+ALTER TABLE codes
+   DROP INDEX codes_code,
+   DROP COLUMN created_at,
+   DROP COLUMN valid_until,
+   DROP COLUMN max_uses,
+   DROP COLUMN uses,
+   DROP COLUMN created_by;
\ No newline at end of file
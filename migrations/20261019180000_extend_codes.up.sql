@@ -0,0 +1,15 @@
+-- Codes that already exist never expire and can be used any number of times, like before
+ALTER TABLE codes
+   ADD COLUMN created_at DATETIME,
+   ADD COLUMN valid_until DATETIME,
+   ADD COLUMN max_uses INTEGER,
+   ADD COLUMN uses INTEGER NOT NULL DEFAULT 0,
+   ADD COLUMN created_by INTEGER REFERENCES users(id),
+   ADD INDEX codes_code (code);
+
+CREATE TABLE IF NOT EXISTS code_redemptions (
+   id INTEGER AUTO_INCREMENT PRIMARY KEY,
+   redeemed_at DATETIME NOT NULL,
+   code_id INTEGER NOT NULL REFERENCES codes(id),
+   user_id INTEGER NOT NULL REFERENCES users(id)
+);
\ No newline at end of file
@@ -0,0 +1,38 @@
+ALTER TABLE users
+   DROP COLUMN deletion_scheduled_at;
+
+ALTER TABLE codes
+   DROP FOREIGN KEY codes_created_by_fk;
+
+ALTER TABLE code_redemptions
+   DROP FOREIGN KEY code_redemptions_user_id_fk,
+   DROP FOREIGN KEY code_redemptions_code_id_fk;
+
+ALTER TABLE app_passwords
+   DROP FOREIGN KEY app_passwords_user_id_fk;
+
+ALTER TABLE two_factor_challenges
+   DROP FOREIGN KEY two_factor_challenges_user_id_fk;
+
+ALTER TABLE recovery_codes
+   DROP FOREIGN KEY recovery_codes_user_id_fk;
+
+ALTER TABLE email_confirmations
+   DROP FOREIGN KEY email_confirmations_user_id_fk;
+
+ALTER TABLE password_resets
+   DROP FOREIGN KEY password_resets_user_id_fk;
+
+ALTER TABLE group_members
+   DROP FOREIGN KEY group_members_group_id_fk,
+   DROP FOREIGN KEY group_members_contact_id_fk;
+
+ALTER TABLE groups
+   DROP FOREIGN KEY groups_user_id_fk;
+
+ALTER TABLE contacts
+   DROP FOREIGN KEY contacts_user_id_fk,
+   DROP FOREIGN KEY contacts_contact_id_fk;
+
+ALTER TABLE tokens
+   DROP FOREIGN KEY tokens_user_id_fk;
\ No newline at end of file
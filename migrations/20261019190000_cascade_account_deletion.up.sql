@@ -0,0 +1,46 @@
+-- Rows left behind by earlier account deletions would make the constraints fail
+DELETE FROM group_members WHERE contact_id NOT IN (SELECT id FROM contacts);
+DELETE FROM group_members WHERE group_id NOT IN (SELECT id FROM groups);
+DELETE FROM contacts WHERE contact_id NOT IN (SELECT id FROM users);
+DELETE FROM contacts WHERE user_id NOT IN (SELECT id FROM users);
+DELETE FROM groups WHERE user_id NOT IN (SELECT id FROM users);
+DELETE FROM tokens WHERE user_id NOT IN (SELECT id FROM users);
+
+ALTER TABLE tokens
+   ADD CONSTRAINT tokens_user_id_fk FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+
+ALTER TABLE contacts
+   ADD CONSTRAINT contacts_user_id_fk FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+   ADD CONSTRAINT contacts_contact_id_fk FOREIGN KEY (contact_id) REFERENCES users(id) ON DELETE CASCADE;
+
+ALTER TABLE groups
+   ADD CONSTRAINT groups_user_id_fk FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+
+ALTER TABLE group_members
+   ADD CONSTRAINT group_members_group_id_fk FOREIGN KEY (group_id) REFERENCES groups(id) ON DELETE CASCADE,
+   ADD CONSTRAINT group_members_contact_id_fk FOREIGN KEY (contact_id) REFERENCES contacts(id) ON DELETE CASCADE;
+
+ALTER TABLE password_resets
+   ADD CONSTRAINT password_resets_user_id_fk FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+
+ALTER TABLE email_confirmations
+   ADD CONSTRAINT email_confirmations_user_id_fk FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+
+ALTER TABLE recovery_codes
+   ADD CONSTRAINT recovery_codes_user_id_fk FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+
+ALTER TABLE two_factor_challenges
+   ADD CONSTRAINT two_factor_challenges_user_id_fk FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+
+ALTER TABLE app_passwords
+   ADD CONSTRAINT app_passwords_user_id_fk FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+
+ALTER TABLE code_redemptions
+   ADD CONSTRAINT code_redemptions_user_id_fk FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+   ADD CONSTRAINT code_redemptions_code_id_fk FOREIGN KEY (code_id) REFERENCES codes(id) ON DELETE CASCADE;
+
+ALTER TABLE codes
+   ADD CONSTRAINT codes_created_by_fk FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL;
+
+ALTER TABLE users
+   ADD COLUMN deletion_scheduled_at DATETIME;
\ No newline at end of file
@@ -0,0 +1,12 @@
+ALTER TABLE group_members
+   DROP INDEX group_members_group_id_contact_id;
+
+ALTER TABLE groups
+   DROP INDEX groups_guid;
+
+ALTER TABLE contacts
+   DROP INDEX contacts_user_id_contact_id;
+
+ALTER TABLE users
+   DROP INDEX users_email,
+   DROP INDEX users_guid;
\ No newline at end of file
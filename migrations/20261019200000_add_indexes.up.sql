@@ -0,0 +1,27 @@
+-- Duplicates that could be created by racing commands are merged into the oldest row.
+-- Duplicate users have to be resolved by hand before this migration can run.
+DELETE duplicate FROM group_members duplicate
+   INNER JOIN contacts ON duplicate.contact_id = contacts.id
+   INNER JOIN contacts original ON original.user_id = contacts.user_id
+      AND original.contact_id = contacts.contact_id AND original.id < contacts.id;
+
+DELETE duplicate FROM contacts duplicate
+   INNER JOIN contacts original ON original.user_id = duplicate.user_id
+      AND original.contact_id = duplicate.contact_id AND original.id < duplicate.id;
+
+DELETE duplicate FROM group_members duplicate
+   INNER JOIN group_members original ON original.group_id = duplicate.group_id
+      AND original.contact_id = duplicate.contact_id AND original.id < duplicate.id;
+
+ALTER TABLE users
+   ADD UNIQUE INDEX users_email (email),
+   ADD UNIQUE INDEX users_guid (guid);
+
+ALTER TABLE contacts
+   ADD UNIQUE INDEX contacts_user_id_contact_id (user_id, contact_id);
+
+ALTER TABLE groups
+   ADD INDEX groups_guid (guid);
+
+ALTER TABLE group_members
+   ADD UNIQUE INDEX group_members_group_id_contact_id (group_id, contact_id);
\ No newline at end of file
@@ -0,0 +1,3 @@
+ALTER TABLE users
+   DROP COLUMN list_version,
+   DROP COLUMN list_updated_at;
\ No newline at end of file
@@ -0,0 +1,6 @@
+-- Clients cache the version the old per-connection counter handed them, so existing lists start above anything it could have reached
+ALTER TABLE users
+   ADD COLUMN list_version INT UNSIGNED NOT NULL DEFAULT 1,
+   ADD COLUMN list_updated_at DATETIME(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6);
+
+UPDATE users SET list_version = UNIX_TIMESTAMP();
\ No newline at end of file
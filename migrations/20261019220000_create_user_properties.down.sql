@@ -0,0 +1 @@
+DROP TABLE user_properties;
\ No newline at end of file
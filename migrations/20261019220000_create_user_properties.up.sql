@@ -0,0 +1,11 @@
+-- Phone numbers and mobile settings set with PRP, stored URL-encoded as sent
+CREATE TABLE IF NOT EXISTS user_properties (
+   id INTEGER AUTO_INCREMENT PRIMARY KEY,
+   user_id INTEGER NOT NULL,
+   name VARCHAR(3) NOT NULL,
+   value VARCHAR(100) NOT NULL,
+   UNIQUE INDEX user_properties_user_id_name (user_id, name)
+);
+
+ALTER TABLE user_properties
+   ADD CONSTRAINT user_properties_user_id_fk FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
\ No newline at end of file
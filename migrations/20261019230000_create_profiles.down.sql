@@ -0,0 +1 @@
+DROP TABLE profiles;
\ No newline at end of file
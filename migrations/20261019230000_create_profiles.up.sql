@@ -0,0 +1,16 @@
+-- Member directory entries, searched with FND. Only listed profiles show up in results
+CREATE TABLE IF NOT EXISTS profiles (
+   id INTEGER AUTO_INCREMENT PRIMARY KEY,
+   user_id INTEGER NOT NULL,
+   first_name VARCHAR(64) NOT NULL,
+   last_name VARCHAR(64) NOT NULL,
+   city VARCHAR(64) NOT NULL,
+   state VARCHAR(64) NOT NULL,
+   country VARCHAR(64) NOT NULL,
+   listed BOOLEAN NOT NULL DEFAULT FALSE,
+   UNIQUE INDEX profiles_user_id (user_id),
+   INDEX profiles_name (last_name, first_name)
+);
+
+ALTER TABLE profiles
+   ADD CONSTRAINT profiles_user_id_fk FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE codes
+   DROP COLUMN invited_email;
\ No newline at end of file
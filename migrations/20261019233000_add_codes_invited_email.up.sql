@@ -0,0 +1,3 @@
+-- Codes mailed to someone with SND or when adding an address that isn't registered, which only work for that address
+ALTER TABLE codes
+   ADD COLUMN invited_email VARCHAR(254);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE pending_additions;
\ No newline at end of file
@@ -0,0 +1,12 @@
+-- Users who added someone to their forward list while that person hasn't put them on their allow or block list yet
+CREATE TABLE IF NOT EXISTS pending_additions (
+   id INTEGER AUTO_INCREMENT PRIMARY KEY,
+   user_id INTEGER NOT NULL,
+   added_by INTEGER NOT NULL,
+   added_at DATETIME NOT NULL,
+   UNIQUE INDEX pending_additions_user_id_added_by (user_id, added_by)
+);
+
+ALTER TABLE pending_additions
+   ADD CONSTRAINT pending_additions_user_id_fk FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+   ADD CONSTRAINT pending_additions_added_by_fk FOREIGN KEY (added_by) REFERENCES users(id) ON DELETE CASCADE;
\ No newline at end of file
@@ -0,0 +1,3 @@
+ALTER TABLE app_passwords
+   DROP INDEX app_passwords_user_id_prefix,
+   DROP COLUMN prefix;
\ No newline at end of file
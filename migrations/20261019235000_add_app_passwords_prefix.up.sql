@@ -0,0 +1,6 @@
+-- Existing app passwords have no prefix to look them up by, so they have to be created again
+DELETE FROM app_passwords;
+
+ALTER TABLE app_passwords
+   ADD COLUMN prefix CHAR(4) NOT NULL,
+   ADD INDEX app_passwords_user_id_prefix (user_id, prefix);
\ No newline at end of file
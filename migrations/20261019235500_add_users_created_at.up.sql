@@ -0,0 +1,3 @@
+-- Accounts that existed before this are treated as created now
+ALTER TABLE users
+   ADD COLUMN created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP;
\ No newline at end of file
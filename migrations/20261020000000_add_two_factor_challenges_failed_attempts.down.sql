@@ -0,0 +1,2 @@
+ALTER TABLE two_factor_challenges
+   DROP COLUMN failed_attempts;
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE two_factor_challenges
+   ADD COLUMN failed_attempts INTEGER NOT NULL DEFAULT 0;
\ No newline at end of file
@@ -0,0 +1,259 @@
+use crate::message::Message;
+use chrono::Utc;
+use log::{error, info};
+use sqlx::{MySql, Pool};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Deletes a user and everything that refers to them, then tells their contacts and live sessions
+pub async fn delete(
+    pool: &Pool<MySql>,
+    broadcast_tx: &broadcast::Sender<Message>,
+    user_id: i32,
+) -> Result<(), sqlx::Error> {
+    let user = sqlx::query!("SELECT email, guid FROM users WHERE id = ?", user_id)
+        .fetch_one(pool)
+        .await?;
+
+    // Contacts that have the user on their lists, and whether the user has them on theirs
+    let mut contacts: HashMap<String, bool> = sqlx::query!(
+        "SELECT email FROM contacts INNER JOIN users ON contacts.user_id = users.id
+        WHERE contact_id = ?",
+        user_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|contact| (contact.email, false))
+    .collect();
+
+    for contact in sqlx::query!(
+        "SELECT email FROM contacts INNER JOIN users ON contacts.contact_id = users.id
+        WHERE user_id = ?",
+        user_id
+    )
+    .fetch_all(pool)
+    .await?
+    {
+        contacts.insert(contact.email, true);
+    }
+
+    // Foreign keys cascade as well, but databases created before they existed may still have restricting ones
+    let mut transaction = pool.begin().await?;
+    sqlx::query!(
+        "DELETE FROM group_members WHERE group_id IN (SELECT id FROM groups WHERE user_id = ?)
+        OR contact_id IN (SELECT id FROM contacts WHERE user_id = ? OR contact_id = ?)",
+        user_id,
+        user_id,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    sqlx::query!(
+        "UPDATE users SET list_version = list_version + 1, list_updated_at = ?
+        WHERE id IN (SELECT user_id FROM contacts WHERE contact_id = ?)
+        OR id IN (SELECT contact_id FROM contacts WHERE user_id = ?)",
+        Utc::now().naive_utc(),
+        user_id,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    sqlx::query!(
+        "DELETE FROM contacts WHERE user_id = ? OR contact_id = ?",
+        user_id,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    sqlx::query!("DELETE FROM groups WHERE user_id = ?", user_id)
+        .execute(&mut *transaction)
+        .await?;
+
+    sqlx::query!("DELETE FROM tokens WHERE user_id = ?", user_id)
+        .execute(&mut *transaction)
+        .await?;
+
+    sqlx::query!("DELETE FROM password_resets WHERE user_id = ?", user_id)
+        .execute(&mut *transaction)
+        .await?;
+
+    sqlx::query!("DELETE FROM email_confirmations WHERE user_id = ?", user_id)
+        .execute(&mut *transaction)
+        .await?;
+
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = ?", user_id)
+        .execute(&mut *transaction)
+        .await?;
+
+    sqlx::query!(
+        "DELETE FROM two_factor_challenges WHERE user_id = ?",
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    sqlx::query!("DELETE FROM user_properties WHERE user_id = ?", user_id)
+        .execute(&mut *transaction)
+        .await?;
+
+    sqlx::query!("DELETE FROM profiles WHERE user_id = ?", user_id)
+        .execute(&mut *transaction)
+        .await?;
+
+    sqlx::query!(
+        "DELETE FROM pending_additions WHERE user_id = ? OR added_by = ?",
+        user_id,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    sqlx::query!("DELETE FROM app_passwords WHERE user_id = ?", user_id)
+        .execute(&mut *transaction)
+        .await?;
+
+    sqlx::query!("DELETE FROM code_redemptions WHERE user_id = ?", user_id)
+        .execute(&mut *transaction)
+        .await?;
+
+    // Invites stay valid for whoever they were given to
+    sqlx::query!(
+        "UPDATE codes SET created_by = NULL WHERE created_by = ?",
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    sqlx::query!("DELETE FROM failed_logins WHERE email = ?", user.email)
+        .execute(&mut *transaction)
+        .await?;
+
+    sqlx::query!("DELETE FROM users WHERE id = ?", user_id)
+        .execute(&mut *transaction)
+        .await?;
+
+    transaction.commit().await?;
+    info!("Deleted account {}", user.email);
+
+    let email = Arc::new(user.email);
+    let _ = broadcast_tx.send(Message::ToContact {
+        sender: email.clone(),
+        receiver: email.clone(),
+        message: String::from("DeleteAccount\r\n"),
+    });
+
+    let _ = broadcast_tx.send(Message::EndSessions(email.clone()));
+    for (contact, in_reverse_list) in contacts {
+        let _ = broadcast_tx.send(Message::ToContact {
+            sender: email.clone(),
+            receiver: Arc::new(contact),
+            message: format!("RemoveContact {} {}\r\n", user.guid, in_reverse_list as u8),
+        });
+    }
+
+    Ok(())
+}
+
+/// Logs the user out everywhere and schedules the deletion, which can be undone until then
+pub async fn schedule_deletion(
+    pool: &Pool<MySql>,
+    broadcast_tx: &broadcast::Sender<Message>,
+    user_id: i32,
+    grace_days: i64,
+) -> Result<(), sqlx::Error> {
+    let user = sqlx::query!("SELECT email FROM users WHERE id = ?", user_id)
+        .fetch_one(pool)
+        .await?;
+
+    let mut transaction = pool.begin().await?;
+    sqlx::query!(
+        "UPDATE users SET deletion_scheduled_at = ? WHERE id = ?",
+        Utc::now().naive_utc() + chrono::Duration::days(grace_days),
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    sqlx::query!("DELETE FROM tokens WHERE user_id = ?", user_id)
+        .execute(&mut *transaction)
+        .await?;
+
+    transaction.commit().await?;
+    info!("Scheduled deletion of account {}", user.email);
+
+    let email = Arc::new(user.email);
+    let _ = broadcast_tx.send(Message::ToContact {
+        sender: email.clone(),
+        receiver: email,
+        message: String::from("DeleteAccount\r\n"),
+    });
+
+    Ok(())
+}
+
+/// Deletes accounts that didn't confirm their email address within the confirmation lifetime every hour,
+/// so unconfirmed registrations can't hold on to an address
+pub async fn purge_unverified(
+    pool: Pool<MySql>,
+    broadcast_tx: broadcast::Sender<Message>,
+    lifetime_hours: i64,
+) {
+    let mut interval = tokio::time::interval(PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let users = match sqlx::query!(
+            "SELECT id FROM users WHERE verified = FALSE AND created_at < ?",
+            Utc::now().naive_utc() - chrono::Duration::hours(lifetime_hours)
+        )
+        .fetch_all(&pool)
+        .await
+        {
+            Ok(users) => users,
+            Err(error) => {
+                error!("Could not get unverified accounts: {error}");
+                continue;
+            }
+        };
+
+        for user in users {
+            if let Err(error) = delete(&pool, &broadcast_tx, user.id).await {
+                error!("Could not delete unverified account {}: {error}", user.id);
+            }
+        }
+    }
+}
+
+/// Deletes accounts whose grace period has ended every hour
+pub async fn purge_scheduled(pool: Pool<MySql>, broadcast_tx: broadcast::Sender<Message>) {
+    let mut interval = tokio::time::interval(PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let users = match sqlx::query!(
+            "SELECT id FROM users WHERE deletion_scheduled_at <= ?",
+            Utc::now().naive_utc()
+        )
+        .fetch_all(&pool)
+        .await
+        {
+            Ok(users) => users,
+            Err(error) => {
+                error!("Could not get accounts scheduled for deletion: {error}");
+                continue;
+            }
+        };
+
+        for user in users {
+            if let Err(error) = delete(&pool, &broadcast_tx, user.id).await {
+                error!("Could not delete account {}: {error}", user.id);
+            }
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use crate::errors::control_error::ControlError;
+use crate::models::transient::authenticated_user::AuthenticatedUser;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt};
+
+const MAX_LINE_LENGTH: u64 = 1024;
+const MAX_PAYLOAD_LENGTH: usize = 65536;
+
+/// Commands followed by a payload, whose length is their last argument
+const PAYLOAD_COMMANDS: [&str; 3] = ["TOC", "UDT", "PAG"];
+
+pub struct ControlFrame {
+    pub args: Vec<String>,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UserDetailsPayload {
+    pub authenticated_user: Option<AuthenticatedUser>,
+    pub protocol_version: Option<u32>,
+}
+
+impl ControlFrame {
+    pub fn command(&self) -> &str {
+        self.args.first().map(String::as_str).unwrap_or_default()
+    }
+
+    pub fn arg(&self, index: usize) -> Result<&str, ControlError> {
+        self.args
+            .get(index)
+            .map(String::as_str)
+            .ok_or(ControlError::InvalidFrame(self.args.join(" ")))
+    }
+}
+
+/// Reads a single line, refusing payload commands, for use before NOD has succeeded
+pub async fn read_command(
+    rd: &mut (impl AsyncBufRead + Unpin),
+) -> Result<ControlFrame, ControlError> {
+    let (line, args) = read_line(rd).await?;
+    if PAYLOAD_COMMANDS.contains(&args[0].as_str()) {
+        return Err(ControlError::InvalidFrame(line));
+    }
+
+    Ok(ControlFrame {
+        args,
+        payload: Vec::new(),
+    })
+}
+
+pub async fn read_frame(
+    rd: &mut (impl AsyncBufRead + Unpin),
+) -> Result<ControlFrame, ControlError> {
+    let (line, args) = read_line(rd).await?;
+    let mut payload = Vec::new();
+
+    if PAYLOAD_COMMANDS.contains(&args[0].as_str()) {
+        let length = args
+            .last()
+            .and_then(|length| length.parse::<usize>().ok())
+            .filter(|length| *length <= MAX_PAYLOAD_LENGTH)
+            .ok_or(ControlError::InvalidFrame(line.clone()))?;
+
+        payload.resize(length, 0);
+        rd.read_exact(&mut payload).await?;
+    }
+
+    Ok(ControlFrame { args, payload })
+}
+
+async fn read_line(
+    rd: &mut (impl AsyncBufRead + Unpin),
+) -> Result<(String, Vec<String>), ControlError> {
+    let mut line = String::new();
+    if (&mut *rd)
+        .take(MAX_LINE_LENGTH)
+        .read_line(&mut line)
+        .await?
+        == 0
+    {
+        return Err(ControlError::Disconnected);
+    }
+
+    if !line.ends_with('\n') {
+        return Err(ControlError::InvalidFrame(line));
+    }
+
+    let args: Vec<String> = line.trim().split(' ').map(str::to_string).collect();
+    Ok((line, args))
+}
@@ -0,0 +1,268 @@
+use super::control_frame::{UserDetailsPayload, read_command, read_frame};
+use crate::config::Config;
+use crate::errors::control_error::ControlError;
+use crate::message::Message;
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use log::{error, info, warn};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// (user whose details or mobile status were requested, user who requested them)
+type PendingDetails = Arc<Mutex<HashSet<(Arc<String>, Arc<String>)>>>;
+
+pub async fn listen(broadcast_tx: broadcast::Sender<Message>, config: Arc<Config>) {
+    let Some(bind_address) = config.cluster.control_bind_address.as_ref() else {
+        return;
+    };
+
+    let listener = TcpListener::bind(bind_address)
+        .await
+        .expect("Could not bind Switchboard control server");
+
+    info!("Switchboard control server listening on {bind_address}");
+
+    loop {
+        let (socket, peer_address) = match listener.accept().await {
+            Ok(client) => client,
+            Err(error) => {
+                error!("Could not get socket from accepted control connection: {error}");
+                continue;
+            }
+        };
+
+        let broadcast_tx = broadcast_tx.clone();
+        let config = config.clone();
+
+        tokio::spawn(async move {
+            if let Err(error) = handle_node(socket, broadcast_tx, config).await {
+                warn!("Switchboard node {peer_address} disconnected: {error}");
+            }
+        });
+    }
+}
+
+fn secrets_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+async fn handle_node(
+    socket: TcpStream,
+    broadcast_tx: broadcast::Sender<Message>,
+    config: Arc<Config>,
+) -> Result<(), ControlError> {
+    let (rd, mut wr) = socket.into_split();
+    let mut rd = BufReader::new(rd);
+
+    let frame = read_command(&mut rd).await?;
+    if frame.command() != "NOD"
+        || config.cluster.secret.is_empty()
+        || !secrets_match(frame.arg(1)?, &config.cluster.secret)
+    {
+        wr.write_all(b"NOD ERR\r\n").await?;
+        return Err(ControlError::InvalidSecret);
+    }
+
+    let address = Arc::new(frame.arg(2)?.to_string());
+    let node_id = Arc::new(format!("{:08}", OsRng.next_u32()));
+    let (node_tx, node_rx) = broadcast::channel::<Message>(config.channels.session_capacity);
+    let broadcast_rx = broadcast_tx.subscribe();
+
+    broadcast_tx.send(Message::AddSwitchboard {
+        key: node_id.clone(),
+        address: address.clone(),
+        value: node_tx,
+    })?;
+
+    wr.write_all(format!("NOD OK {node_id}\r\n").as_bytes())
+        .await?;
+
+    info!("Switchboard node {node_id} registered at {address}");
+
+    let pending_details: PendingDetails = Arc::new(Mutex::new(HashSet::new()));
+    let pending_mobile: PendingDetails = Arc::new(Mutex::new(HashSet::new()));
+    let result = tokio::select! {
+        result = read_node(
+            &mut rd,
+            &broadcast_tx,
+            &node_id,
+            &address,
+            &pending_details,
+            &pending_mobile,
+        ) => result,
+        result = write_node(&mut wr, node_rx, broadcast_rx, &pending_details, &pending_mobile) => result,
+    };
+
+    broadcast_tx.send(Message::RemoveSwitchboard(node_id))?;
+    result
+}
+
+async fn read_node(
+    rd: &mut BufReader<OwnedReadHalf>,
+    broadcast_tx: &broadcast::Sender<Message>,
+    node_id: &Arc<String>,
+    address: &Arc<String>,
+    pending_details: &PendingDetails,
+    pending_mobile: &PendingDetails,
+) -> Result<(), ControlError> {
+    loop {
+        let frame = read_frame(rd).await?;
+        match frame.command() {
+            "LOD" => {
+                let load = frame
+                    .arg(1)?
+                    .parse::<usize>()
+                    .or(Err(ControlError::InvalidFrame(frame.args.join(" "))))?;
+
+                broadcast_tx.send(Message::SwitchboardLoad {
+                    key: node_id.clone(),
+                    value: load,
+                })?;
+            }
+
+            "SES" => {
+                let cki_string = Arc::new(frame.arg(1)?.to_string());
+                let address = if frame.arg(2)? == "OK" {
+                    Some(address.clone())
+                } else {
+                    None
+                };
+
+                broadcast_tx.send(Message::SessionAssigned {
+                    key: cki_string,
+                    address,
+                })?;
+            }
+
+            "TOC" => {
+                let sender = Arc::new(frame.arg(1)?.to_string());
+                let receiver = Arc::new(frame.arg(2)?.to_string());
+                let message = String::from_utf8(frame.payload)
+                    .or(Err(ControlError::InvalidFrame(frame.args.join(" "))))?;
+
+                if message == "GetUserDetails"
+                    && let Ok(mut pending_details) = pending_details.lock()
+                {
+                    pending_details.insert((receiver.clone(), sender.clone()));
+                }
+
+                broadcast_tx.send(Message::ToContact {
+                    sender,
+                    receiver,
+                    message,
+                })?;
+            }
+
+            "MOB" => {
+                let sender = Arc::new(frame.arg(1)?.to_string());
+                let receiver = Arc::new(frame.arg(2)?.to_string());
+                if let Ok(mut pending_mobile) = pending_mobile.lock() {
+                    pending_mobile.insert((receiver.clone(), sender.clone()));
+                }
+
+                broadcast_tx.send(Message::GetMobile { sender, receiver })?;
+            }
+
+            "PAG" => {
+                let sender = Arc::new(frame.arg(1)?.to_string());
+                let receiver = Arc::new(frame.arg(2)?.to_string());
+                let message = String::from_utf8(frame.payload)
+                    .or(Err(ControlError::InvalidFrame(frame.args.join(" "))))?;
+
+                broadcast_tx.send(Message::Page {
+                    sender,
+                    receiver,
+                    message,
+                })?;
+            }
+
+            _ => return Err(ControlError::InvalidFrame(frame.args.join(" "))),
+        }
+    }
+}
+
+async fn write_node(
+    wr: &mut OwnedWriteHalf,
+    mut node_rx: broadcast::Receiver<Message>,
+    mut broadcast_rx: broadcast::Receiver<Message>,
+    pending_details: &PendingDetails,
+    pending_mobile: &PendingDetails,
+) -> Result<(), ControlError> {
+    loop {
+        tokio::select! {
+            message = node_rx.recv() => {
+                let message = match message {
+                    Ok(message) => message,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(_) => return Err(ControlError::Disconnected),
+                };
+
+                match message {
+                    Message::CreateSession { session_id, cki_string, email } => {
+                        wr.write_all(format!("SES {cki_string} {session_id} {email}\r\n").as_bytes()).await?;
+                    }
+
+                    Message::SetDisplayName { email, display_name } => {
+                        wr.write_all(format!("NAM {email} {display_name}\r\n").as_bytes()).await?;
+                    }
+
+                    Message::SetBlocked { email, contact, blocked } => {
+                        wr.write_all(format!("BLK {email} {contact} {}\r\n", u8::from(blocked)).as_bytes()).await?;
+                    }
+
+                    Message::EndSessions(email) => {
+                        wr.write_all(format!("END {email}\r\n").as_bytes()).await?;
+                    }
+
+                    _ => (),
+                }
+            }
+
+            message = broadcast_rx.recv() => {
+                let message = match message {
+                    Ok(message) => message,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(_) => return Err(ControlError::Disconnected),
+                };
+
+                match message {
+                    Message::UserDetails { sender, receiver, authenticated_user, protocol_version } => {
+                        let requested = pending_details
+                            .lock()
+                            .map(|mut pending_details| pending_details.remove(&(sender.clone(), receiver.clone())))
+                            .unwrap_or(false);
+
+                        if !requested {
+                            continue;
+                        }
+
+                        let payload = serde_json::to_vec(&UserDetailsPayload { authenticated_user, protocol_version })?;
+                        wr.write_all(format!("UDT {sender} {receiver} {}\r\n", payload.len()).as_bytes()).await?;
+                        wr.write_all(&payload).await?;
+                    }
+
+                    Message::Mobile { sender, receiver, reachable } => {
+                        let requested = pending_mobile
+                            .lock()
+                            .map(|mut pending_mobile| pending_mobile.remove(&(sender.clone(), receiver.clone())))
+                            .unwrap_or(false);
+
+                        if requested {
+                            wr.write_all(format!("MBL {sender} {receiver} {}\r\n", u8::from(reachable)).as_bytes()).await?;
+                        }
+                    }
+
+                    _ => (),
+                }
+            }
+        }
+    }
+}
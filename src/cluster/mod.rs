@@ -0,0 +1,4 @@
+pub mod control_frame;
+pub mod control_server;
+pub mod node;
+pub mod registered_node;
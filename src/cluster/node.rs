@@ -0,0 +1,359 @@
+use super::control_frame::{UserDetailsPayload, read_command, read_frame};
+use crate::config::Config;
+use crate::errors::control_error::ControlError;
+use crate::limits::connection_limiter::ConnectionLimiter;
+use crate::limits::session_limiter::SessionLimiter;
+use crate::message::Message;
+use crate::switchboard::{
+    session::{self, Session},
+    switchboard,
+};
+use log::{error, info};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Runs a switchboard-only process that takes its sessions from the Notification Server's control server
+pub async fn run(config: Arc<Config>) {
+    let listener = TcpListener::bind(&config.switchboard.bind_address)
+        .await
+        .expect("Could not bind Switchboard");
+
+    info!(
+        "Switchboard node listening on {}",
+        config.switchboard.bind_address
+    );
+
+    let (tx, _) = broadcast::channel::<Message>(config.channels.broadcast_capacity);
+    tokio::spawn(manage_sessions(tx.clone(), config.clone()));
+    tokio::spawn(accept_clients(
+        listener,
+        tx.clone(),
+        config.clone(),
+        ConnectionLimiter::new(config.limits.connections_per_ip),
+        SessionLimiter::new(config.limits.sessions_per_user),
+    ));
+
+    loop {
+        if let Err(error) = connect(&tx, &config).await {
+            error!("Lost connection to the Notification Server control server: {error}");
+        }
+
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+async fn accept_clients(
+    listener: TcpListener,
+    tx: broadcast::Sender<Message>,
+    config: Arc<Config>,
+    connection_limiter: ConnectionLimiter,
+    session_limiter: SessionLimiter,
+) {
+    loop {
+        let (socket, accepted_address) = match listener.accept().await {
+            Ok(client) => client,
+            Err(error) => {
+                error!("Could not get socket from accepted Switchboard connection: {error}");
+                continue;
+            }
+        };
+
+        tokio::spawn(switchboard::serve(
+            socket,
+            accepted_address,
+            tx.clone(),
+            config.clone(),
+            connection_limiter.clone(),
+            session_limiter.clone(),
+        ));
+    }
+}
+
+async fn manage_sessions(tx: broadcast::Sender<Message>, config: Arc<Config>) {
+    let mut rx = tx.subscribe();
+    let mut sessions: HashMap<Arc<String>, Session> = HashMap::new();
+    let mut tickets: HashMap<Arc<String>, Arc<String>> = HashMap::new();
+    let ticket_lifetime = Duration::from_secs(config.switchboard.ticket_lifetime_seconds);
+    let mut session_sweep = tokio::time::interval(session::SWEEP_INTERVAL);
+
+    loop {
+        let message = tokio::select! {
+            message = rx.recv() => match message {
+                Ok(message) => message,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(error) => {
+                    error!("Could not receive message from thread: {error}");
+                    break;
+                }
+            },
+
+            _ = session_sweep.tick() => {
+                let count = sessions.len();
+                session::sweep(&mut sessions, &mut tickets, ticket_lifetime);
+                if sessions.len() != count {
+                    report_load(&tx, &config, sessions.len());
+                }
+
+                continue;
+            }
+        };
+
+        match message {
+            Message::GetSession(key) => {
+                let session = session::find(&sessions, &tickets, &key);
+                if let Err(error) = tx.send(Message::Session {
+                    key: key.clone(),
+                    value: session.cloned(),
+                }) {
+                    error!("Could not send session to {key}: {error}");
+                }
+
+                continue;
+            }
+
+            Message::CreateSession {
+                session_id,
+                cki_string,
+                email,
+            } => {
+                let session = Session::new(
+                    session_id,
+                    cki_string.clone(),
+                    email,
+                    config.channels.session_capacity,
+                    ticket_lifetime,
+                );
+
+                sessions.insert(cki_string.clone(), session);
+                if let Err(error) = tx.send(Message::SessionAssigned {
+                    key: cki_string.clone(),
+                    address: Some(Arc::new(config.switchboard_address())),
+                }) {
+                    error!("Could not confirm session {cki_string}: {error}");
+                }
+            }
+
+            Message::SetSession { key, value } => {
+                sessions.insert(key, value);
+            }
+
+            Message::SetDisplayName {
+                email,
+                display_name,
+            } => {
+                for session in sessions.values() {
+                    if let Ok(mut principals) = session.principals.lock()
+                        && let Some(principal) = principals.get_mut(&email)
+                    {
+                        principal.display_name = display_name.clone();
+                    }
+                }
+
+                continue;
+            }
+
+            Message::SetBlocked {
+                email,
+                contact,
+                blocked,
+            } => {
+                for session in sessions.values() {
+                    if let Ok(mut principals) = session.principals.lock()
+                        && let Some(principal) = principals.get_mut(&email)
+                    {
+                        if blocked {
+                            principal.blocked.insert(contact.clone());
+                        } else {
+                            principal.blocked.remove(&contact);
+                        }
+                    }
+                }
+
+                continue;
+            }
+
+            Message::RemoveSession(key) => {
+                session::remove(&mut sessions, &mut tickets, &key);
+            }
+
+            Message::EndSessions(email) => {
+                session::end_sessions(&sessions, &email);
+                continue;
+            }
+
+            Message::SetTicket { key, value } => {
+                tickets.insert(key, value);
+                continue;
+            }
+
+            Message::RemoveTicket(key) => {
+                tickets.remove(&key);
+                continue;
+            }
+
+            _ => continue,
+        };
+
+        report_load(&tx, &config, sessions.len());
+    }
+}
+
+fn report_load(tx: &broadcast::Sender<Message>, config: &Config, load: usize) {
+    if let Err(error) = tx.send(Message::SwitchboardLoad {
+        key: Arc::new(config.switchboard_address()),
+        value: load,
+    }) {
+        error!("Could not report switchboard load: {error}");
+    }
+}
+
+async fn connect(tx: &broadcast::Sender<Message>, config: &Config) -> Result<(), ControlError> {
+    let socket = TcpStream::connect(&config.cluster.control_address).await?;
+    let (rd, mut wr) = socket.into_split();
+    let mut rd = BufReader::new(rd);
+    let bus_rx = tx.subscribe();
+
+    wr.write_all(
+        format!(
+            "NOD {} {}\r\n",
+            config.cluster.secret,
+            config.switchboard_address()
+        )
+        .as_bytes(),
+    )
+    .await?;
+
+    let frame = read_command(&mut rd).await?;
+    if frame.command() != "NOD" || frame.arg(1)? != "OK" {
+        return Err(ControlError::Rejected);
+    }
+
+    info!(
+        "Registered with the Notification Server at {} as node {}",
+        config.cluster.control_address,
+        frame.arg(2)?
+    );
+
+    tokio::select! {
+        result = read_control(&mut rd, tx) => result,
+        result = write_control(&mut wr, bus_rx) => result,
+    }
+}
+
+async fn read_control(
+    rd: &mut BufReader<OwnedReadHalf>,
+    tx: &broadcast::Sender<Message>,
+) -> Result<(), ControlError> {
+    loop {
+        let frame = read_frame(rd).await?;
+        match frame.command() {
+            "SES" => {
+                tx.send(Message::CreateSession {
+                    session_id: Arc::new(frame.arg(2)?.to_string()),
+                    cki_string: Arc::new(frame.arg(1)?.to_string()),
+                    email: Arc::new(frame.arg(3)?.to_string()),
+                })?;
+            }
+
+            "NAM" => {
+                tx.send(Message::SetDisplayName {
+                    email: Arc::new(frame.arg(1)?.to_string()),
+                    display_name: Arc::new(frame.arg(2)?.to_string()),
+                })?;
+            }
+
+            "BLK" => {
+                tx.send(Message::SetBlocked {
+                    email: Arc::new(frame.arg(1)?.to_string()),
+                    contact: Arc::new(frame.arg(2)?.to_string()),
+                    blocked: frame.arg(3)? == "1",
+                })?;
+            }
+
+            "END" => {
+                tx.send(Message::EndSessions(Arc::new(frame.arg(1)?.to_string())))?;
+            }
+
+            "MBL" => {
+                tx.send(Message::Mobile {
+                    sender: Arc::new(frame.arg(1)?.to_string()),
+                    receiver: Arc::new(frame.arg(2)?.to_string()),
+                    reachable: frame.arg(3)? == "1",
+                })?;
+            }
+
+            "UDT" => {
+                let details: UserDetailsPayload = serde_json::from_slice(&frame.payload)?;
+                tx.send(Message::UserDetails {
+                    sender: Arc::new(frame.arg(1)?.to_string()),
+                    receiver: Arc::new(frame.arg(2)?.to_string()),
+                    authenticated_user: details.authenticated_user,
+                    protocol_version: details.protocol_version,
+                })?;
+            }
+
+            _ => return Err(ControlError::InvalidFrame(frame.args.join(" "))),
+        }
+    }
+}
+
+async fn write_control(
+    wr: &mut OwnedWriteHalf,
+    mut bus_rx: broadcast::Receiver<Message>,
+) -> Result<(), ControlError> {
+    loop {
+        let message = match bus_rx.recv().await {
+            Ok(message) => message,
+            Err(RecvError::Lagged(_)) => continue,
+            Err(_) => return Err(ControlError::Disconnected),
+        };
+
+        match message {
+            Message::ToContact {
+                sender,
+                receiver,
+                message,
+            } => {
+                wr.write_all(
+                    format!("TOC {sender} {receiver} {}\r\n{message}", message.len()).as_bytes(),
+                )
+                .await?;
+            }
+
+            Message::GetMobile { sender, receiver } => {
+                wr.write_all(format!("MOB {sender} {receiver}\r\n").as_bytes())
+                    .await?;
+            }
+
+            Message::Page {
+                sender,
+                receiver,
+                message,
+            } => {
+                wr.write_all(
+                    format!("PAG {sender} {receiver} {}\r\n{message}", message.len()).as_bytes(),
+                )
+                .await?;
+            }
+
+            Message::SessionAssigned { key, address } => {
+                let status = if address.is_some() { "OK" } else { "ERR" };
+                wr.write_all(format!("SES {key} {status}\r\n").as_bytes())
+                    .await?;
+            }
+
+            Message::SwitchboardLoad { key: _, value } => {
+                wr.write_all(format!("LOD {value}\r\n").as_bytes()).await?;
+            }
+
+            _ => (),
+        }
+    }
+}
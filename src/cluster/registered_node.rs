@@ -0,0 +1,10 @@
+use crate::message::Message;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+#[derive(Debug)]
+pub struct RegisteredNode {
+    pub address: Arc<String>,
+    pub load: usize,
+    pub node_tx: broadcast::Sender<Message>,
+}
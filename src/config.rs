@@ -0,0 +1,586 @@
+use crate::errors::config_error::ConfigError;
+use serde::Deserialize;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::{env, fs, io::ErrorKind, path::PathBuf, str::FromStr};
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub notification_server: NotificationServerConfig,
+    pub switchboard: SwitchboardConfig,
+    pub http: HttpConfig,
+    pub cvr: CvrConfig,
+    pub tokens: TokensConfig,
+    pub channels: ChannelsConfig,
+    pub features: FeaturesConfig,
+    pub cluster: ClusterConfig,
+    pub limits: LimitsConfig,
+    pub login_attempts: LoginAttemptsConfig,
+    pub mail: MailConfig,
+    pub sms: SmsConfig,
+    pub invites: InvitesConfig,
+    pub accounts: AccountsConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct NotificationServerConfig {
+    pub bind_address: String,
+    pub proxy_protocol: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SwitchboardConfig {
+    pub bind_address: String,
+    pub public_host: String,
+    pub public_port: u16,
+    pub proxy_protocol: bool,
+    pub ticket_lifetime_seconds: u64,
+    pub idle_timeout_seconds: u64,
+    pub max_principals: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct HttpConfig {
+    pub bind_address: String,
+    pub server_domain: String,
+    pub frontend_url: String,
+    pub real_ip_header: Option<String>,
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CvrConfig {
+    pub recommended_version: String,
+    pub minimum_version: String,
+    pub download_url: String,
+    pub info_url: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TokensConfig {
+    pub lifetime_hours: i64,
+    pub secret: String,
+    pub password_reset_lifetime_minutes: i64,
+    pub email_confirmation_lifetime_hours: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ChannelsConfig {
+    pub broadcast_capacity: usize,
+    pub contact_capacity: usize,
+    pub session_capacity: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FeaturesConfig {
+    pub use_registration_codes: bool,
+    pub require_email_verification: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ClusterConfig {
+    pub role: ClusterRole,
+    pub local_switchboard: bool,
+    pub control_bind_address: Option<String>,
+    pub control_address: String,
+    pub secret: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LimitsConfig {
+    pub connections_per_ip: usize,
+    pub authentication_timeout_seconds: u64,
+    pub idle_timeout_seconds: u64,
+    pub commands_per_second: f64,
+    pub command_burst: u32,
+    pub searches_per_minute: u32,
+    pub pages_per_hour: u32,
+    pub sessions_per_user: usize,
+    pub password_resets_per_email: u32,
+    pub password_resets_per_ip: u32,
+    pub imports_per_day: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LoginAttemptsConfig {
+    pub free_attempts: u32,
+    pub backoff_seconds: u64,
+    pub max_backoff_seconds: u64,
+    pub lockout_attempts: u32,
+    pub lockout_minutes: u64,
+    pub audit_retention_days: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct InvitesConfig {
+    pub per_user: i64,
+    pub lifetime_days: i64,
+    pub max_uses: i32,
+    pub emails_per_day: i64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AccountsConfig {
+    pub deletion_grace_days: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MailConfig {
+    pub transport: MailTransportKind,
+    pub from: String,
+    pub directory: Option<PathBuf>,
+    pub smtp: SmtpConfig,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MailTransportKind {
+    #[default]
+    File,
+    Smtp,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub password: String,
+    pub starttls: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SmsConfig {
+    pub gateway: SmsGatewayKind,
+    pub directory: PathBuf,
+    pub http: SmsHttpConfig,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmsGatewayKind {
+    #[default]
+    Spool,
+    Http,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SmsHttpConfig {
+    pub url: String,
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClusterRole {
+    #[default]
+    NotificationServer,
+    Switchboard,
+}
+
+impl Default for NotificationServerConfig {
+    fn default() -> Self {
+        NotificationServerConfig {
+            bind_address: "0.0.0.0:1863".to_string(),
+            proxy_protocol: false,
+        }
+    }
+}
+
+impl Default for SwitchboardConfig {
+    fn default() -> Self {
+        SwitchboardConfig {
+            bind_address: "0.0.0.0:1864".to_string(),
+            public_host: "127.0.0.1".to_string(),
+            public_port: 1864,
+            proxy_protocol: false,
+            ticket_lifetime_seconds: 120,
+            idle_timeout_seconds: 600,
+            max_principals: 20,
+        }
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig {
+            bind_address: "0.0.0.0:3000".to_string(),
+            server_domain: "localhost".to_string(),
+            frontend_url: "http://localhost:4321".to_string(),
+            real_ip_header: None,
+            trusted_proxies: vec![
+                IpAddr::V4(Ipv4Addr::LOCALHOST),
+                IpAddr::V6(Ipv6Addr::LOCALHOST),
+            ],
+        }
+    }
+}
+
+impl Default for CvrConfig {
+    fn default() -> Self {
+        CvrConfig {
+            recommended_version: "1.0.0000".to_string(),
+            minimum_version: "1.0.0000".to_string(),
+            download_url: "https://r2m.camposs.net/storage".to_string(),
+            info_url: "https://r2m.camposs.net".to_string(),
+        }
+    }
+}
+
+impl Default for TokensConfig {
+    fn default() -> Self {
+        TokensConfig {
+            lifetime_hours: 24,
+            secret: String::new(),
+            password_reset_lifetime_minutes: 60,
+            email_confirmation_lifetime_hours: 48,
+        }
+    }
+}
+
+impl Default for ChannelsConfig {
+    fn default() -> Self {
+        ChannelsConfig {
+            broadcast_capacity: 64,
+            contact_capacity: 16,
+            session_capacity: 16,
+        }
+    }
+}
+
+impl Default for FeaturesConfig {
+    fn default() -> Self {
+        FeaturesConfig {
+            use_registration_codes: true,
+            require_email_verification: true,
+        }
+    }
+}
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        ClusterConfig {
+            role: ClusterRole::NotificationServer,
+            local_switchboard: true,
+            control_bind_address: None,
+            control_address: "127.0.0.1:1865".to_string(),
+            secret: String::new(),
+        }
+    }
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        LimitsConfig {
+            connections_per_ip: 16,
+            authentication_timeout_seconds: 60,
+            idle_timeout_seconds: 180,
+            commands_per_second: 10.0,
+            command_burst: 50,
+            searches_per_minute: 5,
+            pages_per_hour: 10,
+            sessions_per_user: 16,
+            password_resets_per_email: 3,
+            password_resets_per_ip: 10,
+            imports_per_day: 3,
+        }
+    }
+}
+
+impl Default for LoginAttemptsConfig {
+    fn default() -> Self {
+        LoginAttemptsConfig {
+            free_attempts: 3,
+            backoff_seconds: 2,
+            max_backoff_seconds: 300,
+            lockout_attempts: 10,
+            lockout_minutes: 15,
+            audit_retention_days: 90,
+        }
+    }
+}
+
+impl Default for InvitesConfig {
+    fn default() -> Self {
+        InvitesConfig {
+            per_user: 5,
+            lifetime_days: 7,
+            max_uses: 1,
+            emails_per_day: 10,
+        }
+    }
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        MailConfig {
+            transport: MailTransportKind::File,
+            from: "R²M <noreply@localhost>".to_string(),
+            directory: None,
+            smtp: SmtpConfig::default(),
+        }
+    }
+}
+
+impl Default for SmtpConfig {
+    fn default() -> Self {
+        SmtpConfig {
+            host: "localhost".to_string(),
+            port: 587,
+            username: String::new(),
+            password: String::new(),
+            starttls: true,
+        }
+    }
+}
+
+impl FromStr for MailTransportKind {
+    type Err = ();
+
+    fn from_str(transport: &str) -> Result<Self, Self::Err> {
+        match transport {
+            "file" => Ok(MailTransportKind::File),
+            "smtp" => Ok(MailTransportKind::Smtp),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Default for SmsConfig {
+    fn default() -> Self {
+        SmsConfig {
+            gateway: SmsGatewayKind::Spool,
+            directory: PathBuf::from("sms"),
+            http: SmsHttpConfig::default(),
+        }
+    }
+}
+
+impl FromStr for SmsGatewayKind {
+    type Err = ();
+
+    fn from_str(gateway: &str) -> Result<Self, Self::Err> {
+        match gateway {
+            "spool" => Ok(SmsGatewayKind::Spool),
+            "http" => Ok(SmsGatewayKind::Http),
+            _ => Err(()),
+        }
+    }
+}
+
+impl FromStr for ClusterRole {
+    type Err = ();
+
+    fn from_str(role: &str) -> Result<Self, Self::Err> {
+        match role {
+            "notification_server" => Ok(ClusterRole::NotificationServer),
+            "switchboard" => Ok(ClusterRole::Switchboard),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the defaults, then the TOML file at `R2M_CONFIG` (or `config.toml`), then environment overrides
+    pub fn load() -> Result<Self, ConfigError> {
+        let (path, required) = match env::var("R2M_CONFIG") {
+            Ok(path) => (path, true),
+            Err(_) => ("config.toml".to_string(), false),
+        };
+
+        let mut config = match fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents)?,
+            Err(error) if error.kind() == ErrorKind::NotFound && !required => Config::default(),
+            Err(error) => return Err(ConfigError::CouldNotReadFile { path, error }),
+        };
+
+        config.apply_env_overrides()?;
+
+        // Tokens, recovery codes and links are stored as hashes keyed with it, a new one would invalidate them all
+        if config.tokens.secret.is_empty() && config.cluster.role == ClusterRole::NotificationServer
+        {
+            return Err(ConfigError::MissingTokenSecret);
+        }
+
+        Ok(config)
+    }
+
+    fn apply_env_overrides(&mut self) -> Result<(), ConfigError> {
+        override_from_env(
+            "R2M_NOTIFICATION_SERVER_BIND_ADDRESS",
+            &mut self.notification_server.bind_address,
+        )?;
+
+        override_from_env(
+            "R2M_NOTIFICATION_SERVER_PROXY_PROTOCOL",
+            &mut self.notification_server.proxy_protocol,
+        )?;
+
+        override_from_env(
+            "R2M_SWITCHBOARD_BIND_ADDRESS",
+            &mut self.switchboard.bind_address,
+        )?;
+
+        override_from_env("SWITCHBOARD_IP", &mut self.switchboard.public_host)?;
+        override_from_env(
+            "R2M_SWITCHBOARD_PUBLIC_PORT",
+            &mut self.switchboard.public_port,
+        )?;
+        override_from_env(
+            "R2M_SWITCHBOARD_PROXY_PROTOCOL",
+            &mut self.switchboard.proxy_protocol,
+        )?;
+        override_from_env(
+            "R2M_SWITCHBOARD_TICKET_LIFETIME_SECONDS",
+            &mut self.switchboard.ticket_lifetime_seconds,
+        )?;
+        override_from_env(
+            "R2M_SWITCHBOARD_IDLE_TIMEOUT_SECONDS",
+            &mut self.switchboard.idle_timeout_seconds,
+        )?;
+        override_from_env(
+            "R2M_SWITCHBOARD_MAX_PRINCIPALS",
+            &mut self.switchboard.max_principals,
+        )?;
+        override_from_env("R2M_HTTP_BIND_ADDRESS", &mut self.http.bind_address)?;
+        override_from_env("SERVER_DOMAIN", &mut self.http.server_domain)?;
+        override_from_env("FRONTEND_URL", &mut self.http.frontend_url)?;
+        if let Ok(header) = env::var("R2M_HTTP_REAL_IP_HEADER") {
+            self.http.real_ip_header = Some(header);
+        }
+
+        if let Ok(value) = env::var("R2M_HTTP_TRUSTED_PROXIES") {
+            self.http.trusted_proxies = value
+                .split(',')
+                .map(|proxy| proxy.trim().parse())
+                .collect::<Result<_, _>>()
+                .or(Err(ConfigError::InvalidOverride {
+                    variable: "R2M_HTTP_TRUSTED_PROXIES",
+                    value,
+                }))?;
+        }
+
+        override_from_env("R2M_TOKEN_LIFETIME_HOURS", &mut self.tokens.lifetime_hours)?;
+        override_from_env("R2M_TOKEN_SECRET", &mut self.tokens.secret)?;
+        override_from_env(
+            "USE_REGISTRATION_CODES",
+            &mut self.features.use_registration_codes,
+        )?;
+        override_from_env(
+            "R2M_REQUIRE_EMAIL_VERIFICATION",
+            &mut self.features.require_email_verification,
+        )?;
+
+        override_from_env("R2M_CLUSTER_ROLE", &mut self.cluster.role)?;
+        override_from_env(
+            "R2M_CLUSTER_LOCAL_SWITCHBOARD",
+            &mut self.cluster.local_switchboard,
+        )?;
+
+        if let Ok(address) = env::var("R2M_CLUSTER_CONTROL_BIND_ADDRESS") {
+            self.cluster.control_bind_address = Some(address);
+        }
+
+        override_from_env(
+            "R2M_CLUSTER_CONTROL_ADDRESS",
+            &mut self.cluster.control_address,
+        )?;
+
+        override_from_env("R2M_CLUSTER_SECRET", &mut self.cluster.secret)?;
+
+        override_from_env(
+            "R2M_LIMITS_CONNECTIONS_PER_IP",
+            &mut self.limits.connections_per_ip,
+        )?;
+
+        override_from_env(
+            "R2M_LIMITS_AUTHENTICATION_TIMEOUT_SECONDS",
+            &mut self.limits.authentication_timeout_seconds,
+        )?;
+
+        override_from_env(
+            "R2M_LIMITS_IDLE_TIMEOUT_SECONDS",
+            &mut self.limits.idle_timeout_seconds,
+        )?;
+
+        override_from_env(
+            "R2M_LIMITS_COMMANDS_PER_SECOND",
+            &mut self.limits.commands_per_second,
+        )?;
+
+        override_from_env("R2M_LIMITS_COMMAND_BURST", &mut self.limits.command_burst)?;
+        override_from_env(
+            "R2M_LIMITS_SEARCHES_PER_MINUTE",
+            &mut self.limits.searches_per_minute,
+        )?;
+
+        override_from_env("R2M_LIMITS_PAGES_PER_HOUR", &mut self.limits.pages_per_hour)?;
+
+        override_from_env(
+            "R2M_LIMITS_PASSWORD_RESETS_PER_EMAIL",
+            &mut self.limits.password_resets_per_email,
+        )?;
+
+        override_from_env(
+            "R2M_LIMITS_PASSWORD_RESETS_PER_IP",
+            &mut self.limits.password_resets_per_ip,
+        )?;
+
+        override_from_env(
+            "R2M_LIMITS_SESSIONS_PER_USER",
+            &mut self.limits.sessions_per_user,
+        )?;
+
+        override_from_env(
+            "R2M_LIMITS_IMPORTS_PER_DAY",
+            &mut self.limits.imports_per_day,
+        )?;
+
+        override_from_env("R2M_MAIL_TRANSPORT", &mut self.mail.transport)?;
+        override_from_env("R2M_MAIL_FROM", &mut self.mail.from)?;
+        override_from_env("R2M_MAIL_SMTP_HOST", &mut self.mail.smtp.host)?;
+        override_from_env("R2M_MAIL_SMTP_PORT", &mut self.mail.smtp.port)?;
+        override_from_env("R2M_MAIL_SMTP_USERNAME", &mut self.mail.smtp.username)?;
+        override_from_env("R2M_MAIL_SMTP_PASSWORD", &mut self.mail.smtp.password)?;
+
+        override_from_env("R2M_SMS_GATEWAY", &mut self.sms.gateway)?;
+        override_from_env("R2M_SMS_DIRECTORY", &mut self.sms.directory)?;
+        override_from_env("R2M_SMS_HTTP_URL", &mut self.sms.http.url)?;
+        override_from_env("R2M_SMS_HTTP_TOKEN", &mut self.sms.http.token)?;
+
+        Ok(())
+    }
+
+    pub fn switchboard_address(&self) -> String {
+        format!(
+            "{}:{}",
+            self.switchboard.public_host, self.switchboard.public_port
+        )
+    }
+}
+
+fn override_from_env<T: FromStr>(variable: &'static str, field: &mut T) -> Result<(), ConfigError> {
+    if let Ok(value) = env::var(variable) {
+        *field = value
+            .parse()
+            .or(Err(ConfigError::InvalidOverride { variable, value }))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,145 @@
+use crate::config::Config;
+use crate::errors::email_invitation_error::EmailInvitationError;
+use crate::mail::{mail::Mail, mail_transport::MailTransport, mailer::Mailer};
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{Duration, Utc};
+use email_address::EmailAddress;
+use log::{error, info, trace};
+use sqlx::{MySql, Pool};
+use std::sync::Arc;
+
+/// Mails registration codes to people who aren't on the server yet, from SND or when adding their address
+#[derive(Clone)]
+pub struct EmailInviter {
+    pool: Pool<MySql>,
+    config: Arc<Config>,
+    mailer: Mailer,
+}
+
+impl EmailInviter {
+    pub fn new(pool: Pool<MySql>, config: Arc<Config>, mailer: Mailer) -> Self {
+        EmailInviter {
+            pool,
+            config,
+            mailer,
+        }
+    }
+
+    /// Mails a one-time registration code for `email` on behalf of the user with the `inviter` address.
+    /// Registering with it adds the two users to each other's lists
+    pub async fn send(&self, inviter: &str, email: &str) -> Result<(), EmailInvitationError> {
+        if !EmailAddress::is_valid(email) {
+            return Err(EmailInvitationError::InvalidAddress);
+        }
+
+        // Locking the inviter keeps concurrent invitations from both getting under the daily limit
+        let mut transaction = self.pool.begin().await?;
+        let inviter = sqlx::query!(
+            "SELECT id, email, display_name, verified as `verified: bool` FROM users WHERE email = ? LIMIT 1
+            FOR UPDATE",
+            inviter
+        )
+        .fetch_one(&mut *transaction)
+        .await?;
+
+        if !inviter.verified {
+            return Err(EmailInvitationError::InviterNotVerified);
+        }
+
+        if sqlx::query!("SELECT id FROM users WHERE email = ? LIMIT 1", email)
+            .fetch_optional(&mut *transaction)
+            .await?
+            .is_some()
+        {
+            return Err(EmailInvitationError::AlreadyRegistered);
+        }
+
+        let now = Utc::now().naive_utc();
+
+        // Adding the address again or sending SND after the automatic invitation doesn't mail it twice
+        if sqlx::query!(
+            "SELECT id FROM codes WHERE created_by = ? AND invited_email = ? AND valid_until >= ? AND uses < max_uses
+            LIMIT 1",
+            inviter.id,
+            email,
+            now
+        )
+        .fetch_optional(&mut *transaction)
+        .await?
+        .is_some()
+        {
+            return Ok(());
+        }
+
+        let sent = sqlx::query!(
+            "SELECT COUNT(id) as `count: i64` FROM codes
+            WHERE created_by = ? AND invited_email IS NOT NULL AND created_at >= ?",
+            inviter.id,
+            now - Duration::days(1)
+        )
+        .fetch_one(&mut *transaction)
+        .await?;
+
+        if sent.count >= self.config.invites.emails_per_day {
+            return Err(EmailInvitationError::LimitReached);
+        }
+
+        let mut bytes = [0u8; 12];
+        OsRng.fill_bytes(&mut bytes);
+
+        let code = URL_SAFE_NO_PAD.encode(bytes);
+        sqlx::query!(
+            "INSERT INTO codes (code, created_at, valid_until, max_uses, uses, created_by, invited_email)
+            VALUES (?, ?, ?, ?, ?, ?, ?)",
+            code,
+            now,
+            now + Duration::days(self.config.invites.lifetime_days),
+            1,
+            0,
+            inviter.id,
+            email
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+
+        let display_name = urlencoding::decode(&inviter.display_name)
+            .map(|display_name| display_name.into_owned())
+            .unwrap_or(inviter.display_name);
+
+        let mail = Mail {
+            to: email.to_string(),
+            subject: format!("{display_name} invited you to R²M"),
+            body: format!(
+                "{display_name} ({}) would like to add you as a contact on R²M, a server for MSN Messenger.\r\n\r\nSign up within {} days with the link below and you'll be on each other's contact lists:\r\n\r\n{}/register?code={code}&email={}\r\n\r\nIf you don't know them, you can ignore this email.",
+                inviter.email,
+                self.config.invites.lifetime_days,
+                self.config.http.frontend_url,
+                urlencoding::encode(email)
+            ),
+        };
+
+        let mailer = self.mailer.clone();
+        tokio::spawn(async move {
+            if let Err(error) = mailer.send(&mail).await {
+                error!("Could not send invitation mail to {}: {error}", mail.to);
+            } else {
+                trace!("Sent invitation mail to {}", mail.to);
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Sends the invitation without holding up the reply to the client
+    pub fn send_in_background(&self, inviter: Arc<String>, email: String) {
+        let email_inviter = self.clone();
+        tokio::spawn(async move {
+            if let Err(error) = email_inviter.send(&inviter, &email).await {
+                info!("Did not invite {email} for {inviter}: {error}");
+            }
+        });
+    }
+}
@@ -21,8 +21,6 @@ pub enum CommandError {
     CouldNotCreateNln(CommandGenerationError),
     #[error("Could not create UBX reply: {0}")]
     CouldNotCreateUbx(CommandGenerationError),
-    #[error("Could not create RNG reply: {0}")]
-    CouldNotCreateRng(CommandGenerationError),
     #[error("Could not send to broadcast: {0}")]
     CouldNotSendToBroadcast(SendError<Message>),
     #[error("Could not receive from broadcast: {0}")]
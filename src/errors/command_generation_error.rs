@@ -8,8 +8,6 @@ pub enum CommandGenerationError {
     NoClientId,
     #[error("Original command has no transaction ID")]
     NoTrId,
-    #[error("The Switchboard IP environment variable is not set")]
-    SwitchboardIpNotSet,
     #[error("Could not get personal message")]
     CouldNotGetPersonalMessage,
 }
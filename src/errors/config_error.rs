@@ -0,0 +1,16 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Could not read configuration file {path}: {error}")]
+    CouldNotReadFile { path: String, error: std::io::Error },
+    #[error("Could not parse configuration file: {0}")]
+    CouldNotParse(#[from] toml::de::Error),
+    #[error("No token secret configured, set tokens.secret or R2M_TOKEN_SECRET")]
+    MissingTokenSecret,
+    #[error("Invalid value for {variable}: {value}")]
+    InvalidOverride {
+        variable: &'static str,
+        value: String,
+    },
+}
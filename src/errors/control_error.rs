@@ -0,0 +1,27 @@
+use crate::message::Message;
+use thiserror::Error;
+use tokio::sync::broadcast::error::SendError;
+
+#[derive(Error, Debug)]
+pub enum ControlError {
+    #[error("Control connection closed")]
+    Disconnected,
+    #[error("Invalid control frame: {0}")]
+    InvalidFrame(String),
+    #[error("Switchboard node sent an invalid secret")]
+    InvalidSecret,
+    #[error("Notification Server rejected this switchboard node")]
+    Rejected,
+    #[error("Control connection error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not serialize user details: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("Could not send to broadcast: {0}")]
+    CouldNotSendToBroadcast(Box<SendError<Message>>),
+}
+
+impl From<SendError<Message>> for ControlError {
+    fn from(error: SendError<Message>) -> Self {
+        ControlError::CouldNotSendToBroadcast(Box::new(error))
+    }
+}
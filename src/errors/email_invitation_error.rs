@@ -0,0 +1,15 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum EmailInvitationError {
+    #[error("Invalid email address")]
+    InvalidAddress,
+    #[error("Only confirmed users can send invitations")]
+    InviterNotVerified,
+    #[error("Address is already registered")]
+    AlreadyRegistered,
+    #[error("Daily invitation limit reached")]
+    LimitReached,
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
@@ -0,0 +1,13 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum MailError {
+    #[error("Invalid address: {0}")]
+    InvalidAddress(#[from] lettre::address::AddressError),
+    #[error("Could not build mail: {0}")]
+    CouldNotBuild(#[from] lettre::error::Error),
+    #[error("Could not send mail over SMTP: {0}")]
+    Smtp(#[from] lettre::transport::smtp::Error),
+    #[error("Could not write mail: {0}")]
+    Io(#[from] std::io::Error),
+}
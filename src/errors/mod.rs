@@ -1,7 +1,14 @@
 pub mod command_error;
 pub mod command_generation_error;
+pub mod config_error;
 pub mod contact_verification_error;
+pub mod control_error;
+pub mod email_invitation_error;
 pub mod invitation_error;
+pub mod mail_error;
+pub mod page_error;
+pub mod proxy_protocol_error;
 pub mod receive_split_error;
 pub mod server_error;
+pub mod sms_error;
 pub mod thread_command_error;
@@ -0,0 +1,13 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum PageError {
+    #[error("User not found")]
+    UserNotFound,
+    #[error("User can't be reached on their mobile device")]
+    Unreachable,
+    #[error("Hourly page limit reached")]
+    LimitReached,
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
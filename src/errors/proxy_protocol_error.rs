@@ -0,0 +1,13 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ProxyProtocolError {
+    #[error("Connection did not start with a PROXY protocol header")]
+    MissingHeader,
+    #[error("Invalid PROXY protocol header")]
+    InvalidHeader,
+    #[error("Timed out waiting for the PROXY protocol header")]
+    Timeout,
+    #[error("Could not read PROXY protocol header: {0}")]
+    Io(#[from] std::io::Error),
+}
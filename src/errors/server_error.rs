@@ -14,4 +14,14 @@ pub enum ServerError {
     PrincipalsLockError,
     #[error("Client disconnected")]
     Disconnected,
+    #[error("Client did not authenticate in time")]
+    AuthenticationTimeout,
+    #[error("Client stopped sending pings")]
+    IdleTimeout,
+    #[error("Switchboard session went idle")]
+    SessionIdle,
+    #[error("Client exceeded the command rate limit")]
+    RateLimited,
+    #[error("Account was deleted")]
+    AccountDeleted,
 }
@@ -0,0 +1,13 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SmsError {
+    #[error("No URL configured for the HTTP SMS gateway")]
+    MissingUrl,
+    #[error("Could not send SMS over HTTP: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("SMS gateway rejected the message with {0}")]
+    Rejected(reqwest::StatusCode),
+    #[error("Could not write SMS: {0}")]
+    Io(#[from] std::io::Error),
+}
@@ -10,6 +10,12 @@ pub enum ThreadCommandError {
     CouldNotGetAuthenticatedUser,
     #[error("User logged in on another computer")]
     UserLoggedInOnAnotherComputer,
+    #[error("Token used to log in was revoked")]
+    TokenRevoked,
+    #[error("Account was deleted")]
+    AccountDeleted,
+    #[error("Password was reset")]
+    PasswordReset,
     #[error("Command doesn't have enough arguments: {0}")]
     NotEnoughArguments(String),
 }
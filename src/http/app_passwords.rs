@@ -0,0 +1,278 @@
+use crate::config::Config;
+use crate::tokens;
+use argon2::password_hash::SaltString;
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::header::AUTHORIZATION;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum_serde::macros::{Deserialize, Serialize};
+use chrono::{NaiveDateTime, Utc};
+use serde_json::json;
+use sqlx::{MySql, Pool};
+use std::sync::Arc;
+
+const MAX_APP_PASSWORDS: i64 = 20;
+const APP_PASSWORD_LENGTH: usize = 20;
+
+/// The first characters of an app password are stored in plain text to look it up by on login
+const PREFIX_LENGTH: usize = 4;
+
+#[derive(Deserialize)]
+pub struct CreateAppPassword {
+    label: String,
+}
+
+#[derive(Serialize)]
+pub struct AppPasswordResponse {
+    id: i32,
+    label: String,
+    prefix: String,
+    created_at: NaiveDateTime,
+    last_used_at: Option<NaiveDateTime>,
+}
+
+pub async fn app_passwords(
+    headers: HeaderMap,
+    State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
+) -> impl IntoResponse {
+    let token = headers
+        .get(AUTHORIZATION)
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?
+        .to_str()
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?
+        .replace("Bearer ", "");
+
+    let token = tokens::hash(&config.tokens.secret, &token);
+    let Ok(app_passwords) = sqlx::query!(
+        "SELECT id, label, prefix, created_at, last_used_at FROM app_passwords
+        WHERE user_id = (SELECT user_id FROM tokens WHERE token_hash = ?)
+        ORDER BY created_at DESC",
+        token
+    )
+    .fetch_all(&pool)
+    .await
+    else {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    };
+
+    Ok(Json(
+        app_passwords
+            .into_iter()
+            .map(|app_password| AppPasswordResponse {
+                id: app_password.id,
+                label: app_password.label,
+                prefix: app_password.prefix,
+                created_at: app_password.created_at,
+                last_used_at: app_password.last_used_at,
+            })
+            .collect::<Vec<AppPasswordResponse>>(),
+    ))
+}
+
+/// Creates an app password, which is only shown in this response
+pub async fn create_app_password(
+    headers: HeaderMap,
+    State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
+    Json(payload): Json<CreateAppPassword>,
+) -> impl IntoResponse {
+    let label = payload.label.trim();
+    if label.is_empty() || label.len() > 100 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(String::from(
+                "Label must be between 1 and 100 characters long",
+            )),
+        ));
+    }
+
+    let token = headers
+        .get(AUTHORIZATION)
+        .ok_or((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(String::from("Could not get token")),
+        ))?
+        .to_str()
+        .or(Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(String::from("Could not get token")),
+        )))?
+        .replace("Bearer ", "");
+
+    let token = tokens::hash(&config.tokens.secret, &token);
+    let Ok(user) = sqlx::query!(
+        "SELECT users.id, COUNT(app_passwords.id) as `count: i64` FROM users
+        INNER JOIN tokens ON tokens.user_id = users.id
+        LEFT JOIN app_passwords ON app_passwords.user_id = users.id
+        WHERE token_hash = ? GROUP BY users.id LIMIT 1",
+        token
+    )
+    .fetch_one(&pool)
+    .await
+    else {
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(String::from("Could not get user")),
+        ));
+    };
+
+    if user.count >= MAX_APP_PASSWORDS {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(format!(
+                "Accounts can have at most {MAX_APP_PASSWORDS} app passwords"
+            )),
+        ));
+    }
+
+    let password = generate_password();
+    let prefix = &password[..PREFIX_LENGTH];
+
+    let salt = SaltString::generate(&mut OsRng);
+    let Ok(password_hash) = Argon2::default().hash_password(password.as_bytes(), &salt) else {
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(String::from("Could not hash password")),
+        ));
+    };
+
+    let Ok(result) = sqlx::query!(
+        "INSERT INTO app_passwords (label, prefix, password, created_at, user_id) VALUES (?, ?, ?, ?, ?)",
+        label,
+        prefix,
+        password_hash.to_string(),
+        Utc::now().naive_utc(),
+        user.id
+    )
+    .execute(&pool)
+    .await
+    else {
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(String::from("Could not create app password")),
+        ));
+    };
+
+    Ok(Json(json!({
+        "id": result.last_insert_id(),
+        "label": label,
+        "prefix": prefix,
+        "password": password
+    })))
+}
+
+pub async fn revoke_app_password(
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+    State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
+) -> impl IntoResponse {
+    let token = headers
+        .get(AUTHORIZATION)
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?
+        .to_str()
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?
+        .replace("Bearer ", "");
+
+    let token = tokens::hash(&config.tokens.secret, &token);
+    let result = sqlx::query!(
+        "DELETE FROM app_passwords WHERE id = ? AND user_id = (SELECT user_id FROM tokens WHERE token_hash = ?)",
+        id,
+        token
+    )
+    .execute(&pool)
+    .await
+    .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(Json("App password revoked"))
+}
+
+/// Lowercase letters only, so they're easy to type into old clients.
+/// Bytes past the last multiple of 26 are rejected to keep every letter equally likely
+fn generate_password() -> String {
+    let mut password = String::with_capacity(APP_PASSWORD_LENGTH);
+    while password.len() < APP_PASSWORD_LENGTH {
+        let mut bytes = [0u8; APP_PASSWORD_LENGTH];
+        OsRng.fill_bytes(&mut bytes);
+
+        password.extend(
+            bytes
+                .iter()
+                .filter(|byte| **byte < 26 * 9)
+                .map(|byte| char::from(b'a' + byte % 26))
+                .take(APP_PASSWORD_LENGTH - password.len()),
+        );
+    }
+
+    password
+}
+
+/// Checks a password sent by an MSN client. The main password is only accepted while 2FA is off.
+/// Argon2 is slow on purpose, so hashes are verified on the blocking thread pool
+pub async fn verify_client_password(
+    pool: &Pool<MySql>,
+    user_id: i32,
+    password_hash: &str,
+    totp_enabled: bool,
+    password: &str,
+) -> bool {
+    let prefix = password.get(..PREFIX_LENGTH).unwrap_or_default();
+    let Ok(app_passwords) = sqlx::query!(
+        "SELECT id, password FROM app_passwords WHERE user_id = ? AND prefix = ?",
+        user_id,
+        prefix
+    )
+    .fetch_all(pool)
+    .await
+    else {
+        return false;
+    };
+
+    let password_hash = password_hash.to_string();
+    let password = password.to_string();
+    let verified = tokio::task::spawn_blocking(move || {
+        let argon2 = Argon2::default();
+        let verify = |hash: &str| {
+            PasswordHash::new(hash).is_ok_and(|parsed_hash| {
+                argon2
+                    .verify_password(password.as_bytes(), &parsed_hash)
+                    .is_ok()
+            })
+        };
+
+        if !totp_enabled && verify(&password_hash) {
+            return Some(None);
+        }
+
+        app_passwords
+            .into_iter()
+            .find(|app_password| verify(&app_password.password))
+            .map(|app_password| Some(app_password.id))
+    })
+    .await;
+
+    let app_password_id = match verified {
+        Ok(Some(app_password_id)) => app_password_id,
+        Ok(None) | Err(_) => return false,
+    };
+
+    if let Some(app_password_id) = app_password_id {
+        let _ = sqlx::query!(
+            "UPDATE app_passwords SET last_used_at = ? WHERE id = ?",
+            Utc::now().naive_utc(),
+            app_password_id
+        )
+        .execute(pool)
+        .await;
+    }
+
+    true
+}
@@ -0,0 +1,63 @@
+use crate::config::Config;
+use crate::http::login_attempts::LoginAttempts;
+use crate::http::reset_limiter::ResetLimiter;
+use crate::limits::user_limiter::UserLimiter;
+use crate::mail::mailer::Mailer;
+use crate::message::Message;
+use axum::extract::FromRef;
+use sqlx::{MySql, Pool};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: Pool<MySql>,
+    pub broadcast_tx: broadcast::Sender<Message>,
+    pub config: Arc<Config>,
+    pub login_attempts: LoginAttempts,
+    pub reset_limiter: ResetLimiter,
+    pub import_limiter: UserLimiter,
+    pub mailer: Mailer,
+}
+
+impl FromRef<AppState> for Pool<MySql> {
+    fn from_ref(state: &AppState) -> Self {
+        state.pool.clone()
+    }
+}
+
+impl FromRef<AppState> for broadcast::Sender<Message> {
+    fn from_ref(state: &AppState) -> Self {
+        state.broadcast_tx.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Config> {
+    fn from_ref(state: &AppState) -> Self {
+        state.config.clone()
+    }
+}
+
+impl FromRef<AppState> for LoginAttempts {
+    fn from_ref(state: &AppState) -> Self {
+        state.login_attempts.clone()
+    }
+}
+
+impl FromRef<AppState> for ResetLimiter {
+    fn from_ref(state: &AppState) -> Self {
+        state.reset_limiter.clone()
+    }
+}
+
+impl FromRef<AppState> for UserLimiter {
+    fn from_ref(state: &AppState) -> Self {
+        state.import_limiter.clone()
+    }
+}
+
+impl FromRef<AppState> for Mailer {
+    fn from_ref(state: &AppState) -> Self {
+        state.mailer.clone()
+    }
+}
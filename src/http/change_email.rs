@@ -1,3 +1,7 @@
+use crate::config::Config;
+use crate::http::email_confirmation;
+use crate::http::two_factor;
+use crate::mail::mailer::Mailer;
 use argon2::{Argon2, PasswordHash, PasswordVerifier};
 use axum::Json;
 use axum::extract::State;
@@ -6,16 +10,20 @@ use axum::response::IntoResponse;
 use axum_serde::macros::Deserialize;
 use email_address::EmailAddress;
 use sqlx::{MySql, Pool};
+use std::sync::Arc;
 
 #[derive(Deserialize)]
 pub struct ChangeEmail {
     current_email: String,
     new_email: String,
     password: String,
+    code: Option<String>,
 }
 
 pub async fn change_email(
     State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
+    State(mailer): State<Mailer>,
     Json(payload): Json<ChangeEmail>,
 ) -> impl IntoResponse {
     if payload.current_email == payload.new_email {
@@ -35,7 +43,8 @@ pub async fn change_email(
     }
 
     let Ok(user) = sqlx::query!(
-        "SELECT id, password FROM users WHERE email = ? LIMIT 1",
+        "SELECT id, password, totp_secret, totp_enabled as `totp_enabled: bool` FROM users
+        WHERE email = ? LIMIT 1",
         payload.current_email
     )
     .fetch_one(&pool)
@@ -58,6 +67,57 @@ pub async fn change_email(
             Json(String::from("Password incorrect")),
         )))?;
 
+    if user.totp_enabled {
+        let (Some(secret), Some(code)) = (user.totp_secret, payload.code) else {
+            return Err((
+                StatusCode::UNAUTHORIZED,
+                Json(String::from("Two-factor code required")),
+            ));
+        };
+
+        if !two_factor::verify(
+            &pool,
+            &config,
+            user.id,
+            &payload.current_email,
+            &secret,
+            &code,
+        )
+        .await
+        {
+            return Err((
+                StatusCode::UNAUTHORIZED,
+                Json(String::from("Two-factor code incorrect")),
+            ));
+        }
+    }
+
+    if sqlx::query!(
+        "SELECT id FROM users WHERE email = ? LIMIT 1",
+        payload.new_email
+    )
+    .fetch_one(&pool)
+    .await
+    .is_ok()
+    {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(String::from("Email already in use")),
+        ));
+    }
+
+    // The address only changes once the link sent to it is opened
+    if config.features.require_email_verification {
+        email_confirmation::send(&pool, &config, mailer, user.id, payload.new_email)
+            .await
+            .or(Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(String::from("Could not send confirmation link")),
+            )))?;
+
+        return Ok(Json("Confirmation link sent to the new email"));
+    }
+
     sqlx::query!(
         "UPDATE users SET email = ? WHERE id = ?",
         payload.new_email,
@@ -65,10 +125,16 @@ pub async fn change_email(
     )
     .execute(&pool)
     .await
-    .or(Err((
-        StatusCode::INTERNAL_SERVER_ERROR,
-        Json(String::from("Could not change email")),
-    )))?;
+    .map_err(|error| match error {
+        sqlx::Error::Database(error) if error.is_unique_violation() => (
+            StatusCode::CONFLICT,
+            Json(String::from("Email already in use")),
+        ),
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(String::from("Could not change email")),
+        ),
+    })?;
 
     Ok(Json("Email changed successfully"))
 }
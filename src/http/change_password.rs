@@ -1,3 +1,5 @@
+use crate::config::Config;
+use crate::tokens;
 use argon2::password_hash::SaltString;
 use argon2::password_hash::rand_core::OsRng;
 use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
@@ -8,6 +10,7 @@ use axum::http::{HeaderMap, StatusCode};
 use axum::response::IntoResponse;
 use axum_serde::macros::Deserialize;
 use sqlx::{MySql, Pool};
+use std::sync::Arc;
 
 #[derive(Deserialize)]
 pub struct ChangePassword {
@@ -18,6 +21,7 @@ pub struct ChangePassword {
 pub async fn change_password(
     headers: HeaderMap,
     State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
     Json(payload): Json<ChangePassword>,
 ) -> impl IntoResponse {
     if payload.new_password.len() < 8 {
@@ -51,9 +55,11 @@ pub async fn change_password(
         )))?
         .replace("Bearer ", "");
 
+    let token = tokens::hash(&config.tokens.secret, &token);
+
     let Ok(user) = sqlx::query!(
         "SELECT users.id, password FROM users INNER JOIN tokens ON tokens.user_id = users.id
-        WHERE token = ? LIMIT 1",
+        WHERE token_hash = ? LIMIT 1",
         token
     )
     .fetch_one(&pool)
@@ -101,7 +107,7 @@ pub async fn change_password(
     )))?;
 
     // Log out
-    sqlx::query!("DELETE FROM tokens WHERE token = ?", token)
+    sqlx::query!("DELETE FROM tokens WHERE token_hash = ?", token)
         .execute(&pool)
         .await
         .or(Err((
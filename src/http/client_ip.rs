@@ -0,0 +1,43 @@
+use crate::config::Config;
+use axum::extract::{ConnectInfo, FromRef, FromRequestParts};
+use axum::http::{StatusCode, request::Parts};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+/// Address of the client, taken from `http.real_ip_header` when the connection comes from a trusted proxy
+pub struct ClientIp(pub IpAddr);
+
+impl<S> FromRequestParts<S> for ClientIp
+where
+    Arc<Config>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let config = Arc::<Config>::from_ref(state);
+        let peer_ip = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(address)| address.ip().to_canonical())
+            .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        if !config.http.trusted_proxies.contains(&peer_ip) {
+            return Ok(ClientIp(peer_ip));
+        }
+
+        // Entries on the left are whatever the client sent, the proxy appends the address it saw
+        if let Some(header) = &config.http.real_ip_header
+            && let Some(ip) = parts
+                .headers
+                .get(header)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.rsplit(',').next())
+                .and_then(|ip| ip.trim().parse::<IpAddr>().ok())
+        {
+            return Ok(ClientIp(ip.to_canonical()));
+        }
+
+        Ok(ClientIp(peer_ip))
+    }
+}
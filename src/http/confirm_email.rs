@@ -0,0 +1,151 @@
+use crate::config::Config;
+use crate::http::email_confirmation;
+use crate::mail::mailer::Mailer;
+use crate::tokens;
+use axum::Json;
+use axum::extract::State;
+use axum::http::header::AUTHORIZATION;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use chrono::Utc;
+use log::trace;
+use serde::Deserialize;
+use sqlx::{MySql, Pool};
+use std::sync::Arc;
+
+#[derive(Deserialize)]
+pub struct ConfirmEmail {
+    token: String,
+}
+
+pub async fn confirm_email(
+    State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
+    Json(payload): Json<ConfirmEmail>,
+) -> impl IntoResponse {
+    let Ok(confirmation) = sqlx::query!(
+        "SELECT id, email, user_id FROM email_confirmations WHERE token_hash = ? AND valid_until >= ? LIMIT 1",
+        tokens::hash(&config.tokens.secret, &payload.token),
+        Utc::now().naive_utc()
+    )
+    .fetch_one(&pool)
+    .await
+    else {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(String::from("Confirmation link invalid or expired")),
+        ));
+    };
+
+    // The address may have been taken since the link was sent
+    if sqlx::query!(
+        "SELECT id FROM users WHERE email = ? AND id != ? LIMIT 1",
+        confirmation.email,
+        confirmation.user_id
+    )
+    .fetch_one(&pool)
+    .await
+    .is_ok()
+    {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(String::from("Email already in use")),
+        ));
+    }
+
+    let mut transaction = pool.begin().await.or(Err((
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(String::from("Could not confirm email")),
+    )))?;
+
+    let deleted = sqlx::query!(
+        "DELETE FROM email_confirmations WHERE id = ?",
+        confirmation.id
+    )
+    .execute(&mut *transaction)
+    .await
+    .or(Err((
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(String::from("Could not confirm email")),
+    )))?;
+
+    if deleted.rows_affected() == 0 {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(String::from("Confirmation link invalid or expired")),
+        ));
+    }
+
+    sqlx::query!(
+        "UPDATE users SET email = ?, verified = TRUE WHERE id = ?",
+        confirmation.email,
+        confirmation.user_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(|error| match error {
+        sqlx::Error::Database(error) if error.is_unique_violation() => (
+            StatusCode::CONFLICT,
+            Json(String::from("Email already in use")),
+        ),
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(String::from("Could not confirm email")),
+        ),
+    })?;
+
+    transaction.commit().await.or(Err((
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(String::from("Could not confirm email")),
+    )))?;
+
+    trace!("{} confirmed", confirmation.email);
+    Ok(Json("Email confirmed successfully"))
+}
+
+pub async fn resend_confirmation(
+    headers: HeaderMap,
+    State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
+    State(mailer): State<Mailer>,
+) -> impl IntoResponse {
+    let token = headers
+        .get(AUTHORIZATION)
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?
+        .to_str()
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?
+        .replace("Bearer ", "");
+
+    let token = tokens::hash(&config.tokens.secret, &token);
+
+    let Ok(user) = sqlx::query!(
+        "SELECT users.id, email, verified as `verified: bool` FROM users INNER JOIN tokens ON tokens.user_id = users.id
+        WHERE token_hash = ? LIMIT 1",
+        token
+    )
+    .fetch_one(&pool)
+    .await
+    else {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    };
+
+    // A pending address change is resent to the new address
+    let email = sqlx::query!(
+        "SELECT email FROM email_confirmations WHERE user_id = ? LIMIT 1",
+        user.id
+    )
+    .fetch_one(&pool)
+    .await
+    .map(|confirmation| confirmation.email)
+    .ok();
+
+    let Some(email) = email.or((!user.verified).then_some(user.email)) else {
+        return Err(StatusCode::CONFLICT);
+    };
+
+    email_confirmation::send(&pool, &config, mailer, user.id, email)
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+
+    Ok(Json("Confirmation link sent"))
+}
@@ -1,3 +1,8 @@
+use crate::accounts;
+use crate::config::Config;
+use crate::http::two_factor;
+use crate::message::Message;
+use crate::tokens;
 use argon2::{Argon2, PasswordHash, PasswordVerifier};
 use axum::Json;
 use axum::extract::State;
@@ -6,15 +11,20 @@ use axum::http::{HeaderMap, StatusCode};
 use axum::response::IntoResponse;
 use axum_serde::macros::Deserialize;
 use sqlx::{MySql, Pool};
+use std::sync::Arc;
+use tokio::sync::broadcast;
 
 #[derive(Deserialize)]
 pub struct DeleteAccount {
     password: String,
+    code: Option<String>,
 }
 
 pub async fn delete_account(
     headers: HeaderMap,
     State(pool): State<Pool<MySql>>,
+    State(broadcast_tx): State<broadcast::Sender<Message>>,
+    State(config): State<Arc<Config>>,
     Json(payload): Json<DeleteAccount>,
 ) -> impl IntoResponse {
     let token = headers
@@ -24,9 +34,12 @@ pub async fn delete_account(
         .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?
         .replace("Bearer ", "");
 
+    let token = tokens::hash(&config.tokens.secret, &token);
+
     let Ok(user) = sqlx::query!(
-        "SELECT users.id, password FROM users INNER JOIN tokens ON tokens.user_id = users.id
-        WHERE token = ? LIMIT 1",
+        "SELECT users.id, email, password, totp_secret, totp_enabled as `totp_enabled: bool` FROM users
+        INNER JOIN tokens ON tokens.user_id = users.id
+        WHERE token_hash = ? LIMIT 1",
         token
     )
     .fetch_one(&pool)
@@ -42,25 +55,62 @@ pub async fn delete_account(
         .verify_password(payload.password.as_bytes(), &parsed_hash)
         .or(Err(StatusCode::UNAUTHORIZED))?;
 
-    sqlx::query!("DELETE FROM tokens WHERE user_id = ?", user.id)
-        .execute(&pool)
-        .await
-        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+    if user.totp_enabled {
+        let (Some(secret), Some(code)) = (user.totp_secret, payload.code) else {
+            return Err(StatusCode::UNAUTHORIZED);
+        };
 
-    sqlx::query!("DELETE FROM contacts WHERE user_id = ?", user.id)
-        .execute(&pool)
-        .await
-        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+        if !two_factor::verify(&pool, &config, user.id, &user.email, &secret, &code).await {
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+    }
 
-    sqlx::query!("DELETE FROM groups WHERE user_id = ?", user.id)
-        .execute(&pool)
+    if config.accounts.deletion_grace_days > 0 {
+        accounts::schedule_deletion(
+            &pool,
+            &broadcast_tx,
+            user.id,
+            config.accounts.deletion_grace_days,
+        )
         .await
         .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
 
-    sqlx::query!("DELETE FROM users WHERE id = ?", user.id)
-        .execute(&pool)
+        return Ok(Json("User will be deleted, sign in and restore it to undo"));
+    }
+
+    accounts::delete(&pool, &broadcast_tx, user.id)
         .await
         .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
 
     Ok(Json("User deleted successfully"))
 }
+
+/// Cancels a scheduled deletion
+pub async fn restore_account(
+    headers: HeaderMap,
+    State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
+) -> impl IntoResponse {
+    let token = headers
+        .get(AUTHORIZATION)
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?
+        .to_str()
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?
+        .replace("Bearer ", "");
+
+    let token = tokens::hash(&config.tokens.secret, &token);
+    let result = sqlx::query!(
+        "UPDATE users SET deletion_scheduled_at = NULL
+        WHERE id = (SELECT user_id FROM tokens WHERE token_hash = ?) AND deletion_scheduled_at IS NOT NULL",
+        token
+    )
+    .execute(&pool)
+    .await
+    .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(Json("User restored"))
+}
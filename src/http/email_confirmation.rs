@@ -0,0 +1,57 @@
+use crate::config::Config;
+use crate::mail::{mail::Mail, mail_transport::MailTransport, mailer::Mailer};
+use crate::tokens;
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{Duration, Utc};
+use log::{error, trace};
+use sqlx::{MySql, Pool};
+
+/// Replaces the user's pending confirmation with one for `email` and mails the link to that address
+pub async fn send(
+    pool: &Pool<MySql>,
+    config: &Config,
+    mailer: Mailer,
+    user_id: i32,
+    email: String,
+) -> Result<(), sqlx::Error> {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+
+    let confirmation_token = URL_SAFE_NO_PAD.encode(bytes);
+    let valid_until =
+        Utc::now().naive_utc() + Duration::hours(config.tokens.email_confirmation_lifetime_hours);
+
+    sqlx::query!("DELETE FROM email_confirmations WHERE user_id = ?", user_id)
+        .execute(pool)
+        .await?;
+
+    sqlx::query!(
+        "INSERT INTO email_confirmations (token_hash, email, valid_until, user_id) VALUES (?, ?, ?, ?)",
+        tokens::hash(&config.tokens.secret, &confirmation_token),
+        email,
+        valid_until,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    let mail = Mail {
+        to: email,
+        subject: String::from("Confirm your R²M email address"),
+        body: format!(
+            "Open the link below within {} hours to confirm this email address:\r\n\r\n{}/confirm-email?token={confirmation_token}\r\n\r\nIf you didn't request this, you can ignore this email.",
+            config.tokens.email_confirmation_lifetime_hours, config.http.frontend_url
+        ),
+    };
+
+    tokio::spawn(async move {
+        if let Err(error) = mailer.send(&mail).await {
+            error!("Could not send confirmation mail to {}: {error}", mail.to);
+        } else {
+            trace!("Sent confirmation mail to {}", mail.to);
+        }
+    });
+
+    Ok(())
+}
@@ -0,0 +1,176 @@
+use crate::config::Config;
+use crate::tokens;
+use axum::Json;
+use axum::extract::{Query, State};
+use axum::http::header::{AUTHORIZATION, CONTENT_DISPOSITION, CONTENT_TYPE};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use quick_xml::escape::escape;
+use serde::{Deserialize, Serialize};
+use sqlx::{MySql, Pool};
+use std::sync::Arc;
+
+#[derive(Serialize, Deserialize)]
+pub struct AccountArchive {
+    pub profile: ProfileArchive,
+    pub settings: SettingsArchive,
+    pub groups: Vec<GroupArchive>,
+    pub contacts: Vec<ContactArchive>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ProfileArchive {
+    pub email: String,
+    pub display_name: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SettingsArchive {
+    pub gtc: String,
+    pub blp: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GroupArchive {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ContactArchive {
+    pub email: String,
+    pub display_name: String,
+    pub forward_list: bool,
+    pub allow_list: bool,
+    pub block_list: bool,
+    pub groups: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    format: Option<String>,
+}
+
+/// Exports the account as JSON, or the forward list as a Messenger .ctt file with `?format=ctt`
+pub async fn export(
+    headers: HeaderMap,
+    Query(query): Query<ExportQuery>,
+    State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
+) -> impl IntoResponse {
+    let token = headers
+        .get(AUTHORIZATION)
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?
+        .to_str()
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?
+        .replace("Bearer ", "");
+
+    let token = tokens::hash(&config.tokens.secret, &token);
+    let Ok(user) = sqlx::query!(
+        "SELECT users.id, email, display_name, gtc, blp FROM users
+        INNER JOIN tokens ON tokens.user_id = users.id
+        WHERE token_hash = ? LIMIT 1",
+        token
+    )
+    .fetch_one(&pool)
+    .await
+    else {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    };
+
+    let groups = sqlx::query!(
+        "SELECT id, name FROM groups WHERE user_id = ? ORDER BY id",
+        user.id
+    )
+    .fetch_all(&pool)
+    .await
+    .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+
+    let contacts = sqlx::query!(
+        "SELECT contacts.id, contacts.display_name, email,
+        in_forward_list as `in_forward_list: bool`,
+        in_allow_list as `in_allow_list: bool`,
+        in_block_list as `in_block_list: bool`
+        FROM contacts INNER JOIN users ON contacts.contact_id = users.id
+        WHERE user_id = ? ORDER BY contacts.id",
+        user.id
+    )
+    .fetch_all(&pool)
+    .await
+    .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+
+    if query.format.as_deref() == Some("ctt") {
+        let mut ctt = String::from(
+            "<?xml version=\"1.0\"?>\r\n<messenger>\r\n  <service name=\".NET Messenger Service\">\r\n    <contactlist>\r\n",
+        );
+
+        for contact in contacts.iter().filter(|contact| contact.in_forward_list) {
+            ctt.push_str(&format!(
+                "      <contact>{}</contact>\r\n",
+                escape(&contact.email)
+            ));
+        }
+
+        ctt.push_str("    </contactlist>\r\n  </service>\r\n</messenger>\r\n");
+        return Ok((
+            [
+                (CONTENT_TYPE, "application/xml"),
+                (CONTENT_DISPOSITION, "attachment; filename=\"contacts.ctt\""),
+            ],
+            ctt,
+        )
+            .into_response());
+    }
+
+    let group_members = sqlx::query!(
+        "SELECT group_members.group_id, group_members.contact_id FROM group_members
+        INNER JOIN groups ON group_members.group_id = groups.id
+        WHERE groups.user_id = ?",
+        user.id
+    )
+    .fetch_all(&pool)
+    .await
+    .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+
+    let contacts = contacts
+        .into_iter()
+        .map(|contact| ContactArchive {
+            groups: group_members
+                .iter()
+                .filter(|member| member.contact_id == contact.id)
+                .filter_map(|member| groups.iter().find(|group| group.id == member.group_id))
+                .map(|group| decode(&group.name))
+                .collect(),
+            email: contact.email,
+            display_name: decode(&contact.display_name),
+            forward_list: contact.in_forward_list,
+            allow_list: contact.in_allow_list,
+            block_list: contact.in_block_list,
+        })
+        .collect();
+
+    Ok(Json(AccountArchive {
+        profile: ProfileArchive {
+            email: user.email,
+            display_name: decode(&user.display_name),
+        },
+        settings: SettingsArchive {
+            gtc: user.gtc,
+            blp: user.blp,
+        },
+        groups: groups
+            .iter()
+            .map(|group| GroupArchive {
+                name: decode(&group.name),
+            })
+            .collect(),
+        contacts,
+    })
+    .into_response())
+}
+
+/// Names are stored the way clients send them, URL encoded
+fn decode(name: &str) -> String {
+    urlencoding::decode(name)
+        .map(|name| name.to_string())
+        .unwrap_or_else(|_| name.to_string())
+}
@@ -0,0 +1,106 @@
+use crate::config::Config;
+use crate::http::client_ip::ClientIp;
+use crate::http::reset_limiter::ResetLimiter;
+use crate::mail::{mail::Mail, mail_transport::MailTransport, mailer::Mailer};
+use crate::tokens;
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{Duration, Utc};
+use log::{error, trace};
+use serde::Deserialize;
+use sqlx::{MySql, Pool};
+use std::sync::Arc;
+
+#[derive(Deserialize)]
+pub struct ForgotPassword {
+    email: String,
+}
+
+pub async fn forgot_password(
+    State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
+    State(mailer): State<Mailer>,
+    State(reset_limiter): State<ResetLimiter>,
+    ClientIp(ip): ClientIp,
+    Json(payload): Json<ForgotPassword>,
+) -> impl IntoResponse {
+    // Checked before the lookup, so addresses without an account are limited the same way
+    if !reset_limiter.try_take(&payload.email, ip) {
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(String::from(
+                "Too many reset requests, please try again later",
+            )),
+        );
+    }
+
+    // The same reply is sent whether the account exists or not
+    let reply = (
+        StatusCode::OK,
+        Json(String::from(
+            "If an account with this email exists, a reset link was sent to it",
+        )),
+    );
+
+    let Ok(user) = sqlx::query!(
+        "SELECT id, email FROM users WHERE email = ? LIMIT 1",
+        payload.email
+    )
+    .fetch_one(&pool)
+    .await
+    else {
+        return reply;
+    };
+
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+
+    let reset_token = URL_SAFE_NO_PAD.encode(bytes);
+    let valid_until =
+        Utc::now().naive_utc() + Duration::minutes(config.tokens.password_reset_lifetime_minutes);
+
+    // Only the latest link works
+    if sqlx::query!("DELETE FROM password_resets WHERE user_id = ?", user.id)
+        .execute(&pool)
+        .await
+        .is_err()
+        || sqlx::query!(
+            "INSERT INTO password_resets (token_hash, valid_until, user_id) VALUES (?, ?, ?)",
+            tokens::hash(&config.tokens.secret, &reset_token),
+            valid_until,
+            user.id
+        )
+        .execute(&pool)
+        .await
+        .is_err()
+    {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(String::from("Could not create reset link")),
+        );
+    }
+
+    let mail = Mail {
+        to: user.email,
+        subject: String::from("Reset your R²M password"),
+        body: format!(
+            "A password reset was requested for your account. Open the link below within {} minutes to choose a new password:\r\n\r\n{}/reset-password?token={reset_token}\r\n\r\nIf you didn't request this, you can ignore this email.",
+            config.tokens.password_reset_lifetime_minutes, config.http.frontend_url
+        ),
+    };
+
+    // Sent in the background so response times don't reveal whether the account exists
+    tokio::spawn(async move {
+        if let Err(error) = mailer.send(&mail).await {
+            error!("Could not send password reset mail to {}: {error}", mail.to);
+        } else {
+            trace!("Sent password reset mail to {}", mail.to);
+        }
+    });
+
+    reply
+}
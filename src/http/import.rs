@@ -0,0 +1,233 @@
+use crate::config::Config;
+use crate::errors::command_error::CommandError;
+use crate::http::export::AccountArchive;
+use crate::limits::user_limiter::UserLimiter;
+use crate::message::Message;
+use crate::models::transient::authenticated_user::AuthenticatedUser;
+use crate::notification_server::commands::{
+    adc::Adc, adg::Adg, blp::Blp, gtc::Gtc, traits::user_command::UserCommand,
+};
+use crate::tokens;
+use axum::Json;
+use axum::extract::State;
+use axum::http::header::AUTHORIZATION;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use email_address::EmailAddress;
+use serde_json::json;
+use sqlx::{MySql, Pool};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+const MAX_IMPORTED_CONTACTS: usize = 1000;
+const MAX_IMPORTED_GROUPS: usize = 100;
+
+// The same commands an MSNP13 client sends, so contacts get the usual reverse list notifications
+const PROTOCOL_VERSION: u32 = 13;
+
+/// Recreates the groups and contacts from an export. Contacts already in the lists are left as they are.
+/// Each entry is applied on its own like the client commands it replays, so the reply lists the ones that were
+pub async fn import(
+    headers: HeaderMap,
+    State(pool): State<Pool<MySql>>,
+    State(broadcast_tx): State<broadcast::Sender<Message>>,
+    State(config): State<Arc<Config>>,
+    State(import_limiter): State<UserLimiter>,
+    Json(payload): Json<AccountArchive>,
+) -> impl IntoResponse {
+    if payload.contacts.len() > MAX_IMPORTED_CONTACTS || payload.groups.len() > MAX_IMPORTED_GROUPS
+    {
+        return Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            Json(format!(
+                "Imports can have at most {MAX_IMPORTED_CONTACTS} contacts and {MAX_IMPORTED_GROUPS} groups"
+            )),
+        ));
+    }
+
+    let token = headers
+        .get(AUTHORIZATION)
+        .ok_or((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(String::from("Could not get token")),
+        ))?
+        .to_str()
+        .or(Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(String::from("Could not get token")),
+        )))?
+        .replace("Bearer ", "");
+
+    let token = tokens::hash(&config.tokens.secret, &token);
+    let Ok(database_user) = sqlx::query!(
+        "SELECT users.id, email, display_name, blp FROM users
+        INNER JOIN tokens ON tokens.user_id = users.id
+        WHERE token_hash = ? LIMIT 1",
+        token
+    )
+    .fetch_one(&pool)
+    .await
+    else {
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(String::from("Could not get user")),
+        ));
+    };
+
+    let mut user = AuthenticatedUser::new(Arc::new(database_user.email));
+    if !import_limiter.try_take(&user.email) {
+        return Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(String::from("Too many imports, please try again later")),
+        ));
+    }
+
+    user.display_name = Arc::new(database_user.display_name);
+    user.blp = Arc::new(database_user.blp);
+
+    let mut version_number = 0;
+    let gtc = Gtc::new(pool.clone());
+    let blp = Blp::new(pool.clone());
+    let adg = Adg::new(pool.clone());
+    let adc = Adc::new(pool.clone(), broadcast_tx, None);
+
+    let mut imported_settings = Vec::new();
+    if gtc
+        .handle(
+            PROTOCOL_VERSION,
+            &format!("GTC 1 {}", payload.settings.gtc),
+            &mut user,
+            &mut version_number,
+        )
+        .await
+        .is_ok()
+    {
+        imported_settings.push("gtc");
+    }
+
+    if blp
+        .handle(
+            PROTOCOL_VERSION,
+            &format!("BLP 1 {}", payload.settings.blp),
+            &mut user,
+            &mut version_number,
+        )
+        .await
+        .is_ok()
+    {
+        imported_settings.push("blp");
+    }
+
+    let mut imported_groups = Vec::new();
+    for group in &payload.groups {
+        let name = urlencoding::encode(&group.name);
+        if adg
+            .handle(
+                PROTOCOL_VERSION,
+                &format!("ADG 1 {name} 0"),
+                &mut user,
+                &mut version_number,
+            )
+            .await
+            .is_ok()
+        {
+            imported_groups.push(group.name.clone());
+        }
+    }
+
+    let Ok(groups) = sqlx::query!(
+        "SELECT name, guid FROM groups WHERE user_id = ?",
+        database_user.id
+    )
+    .fetch_all(&pool)
+    .await
+    else {
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(String::from("Could not get groups")),
+        ));
+    };
+
+    let mut imported_contacts = Vec::new();
+    let mut skipped = Vec::new();
+    for contact in &payload.contacts {
+        if !EmailAddress::is_valid(&contact.email) || contact.email == *user.email {
+            skipped.push(contact.email.clone());
+            continue;
+        }
+
+        let display_name = urlencoding::encode(&contact.display_name);
+        let lists = [
+            (
+                contact.forward_list,
+                format!("FL N={} F={display_name}", contact.email),
+            ),
+            (contact.allow_list, format!("AL N={}", contact.email)),
+            (contact.block_list, format!("BL N={}", contact.email)),
+        ];
+
+        let mut found = true;
+        for (_, list) in lists.iter().filter(|(in_list, _)| *in_list) {
+            match adc
+                .handle(
+                    PROTOCOL_VERSION,
+                    &format!("ADC 1 {list}"),
+                    &mut user,
+                    &mut version_number,
+                )
+                .await
+            {
+                Ok(_) => (),
+                Err(CommandError::Reply(reply)) if reply.starts_with("215") => (),
+                Err(_) => {
+                    found = false;
+                    break;
+                }
+            }
+        }
+
+        if !found {
+            skipped.push(contact.email.clone());
+            continue;
+        }
+
+        imported_contacts.push(contact.email.clone());
+        if !contact.forward_list || contact.groups.is_empty() {
+            continue;
+        }
+
+        let Ok(contact_user) = sqlx::query!(
+            "SELECT guid FROM users WHERE email = ? LIMIT 1",
+            contact.email
+        )
+        .fetch_one(&pool)
+        .await
+        else {
+            continue;
+        };
+
+        for group_name in &contact.groups {
+            let name = urlencoding::encode(group_name);
+            let Some(group) = groups.iter().find(|group| group.name == name) else {
+                continue;
+            };
+
+            let _ = adc
+                .handle(
+                    PROTOCOL_VERSION,
+                    &format!("ADC 1 FL C={} {}", contact_user.guid, group.guid),
+                    &mut user,
+                    &mut version_number,
+                )
+                .await;
+        }
+    }
+
+    Ok(Json(json!({
+        "imported_settings": imported_settings,
+        "imported_groups": imported_groups,
+        "imported_contacts": imported_contacts,
+        "skipped": skipped,
+        "message": "Sign in again to see the imported contacts"
+    })))
+}
@@ -0,0 +1,224 @@
+use crate::config::Config;
+use crate::tokens;
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::header::AUTHORIZATION;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum_serde::macros::Serialize;
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{Duration, NaiveDateTime, Utc};
+use sqlx::{MySql, Pool};
+use std::sync::Arc;
+
+#[derive(Serialize)]
+pub struct InviteResponse {
+    id: i32,
+    code: String,
+    created_at: Option<NaiveDateTime>,
+    valid_until: Option<NaiveDateTime>,
+    uses: i32,
+    max_uses: Option<i32>,
+    redeemed_by: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct InvitesResponse {
+    remaining: i64,
+    invites: Vec<InviteResponse>,
+}
+
+pub async fn invites(
+    headers: HeaderMap,
+    State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
+) -> impl IntoResponse {
+    let token = headers
+        .get(AUTHORIZATION)
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?
+        .to_str()
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?
+        .replace("Bearer ", "");
+
+    let token = tokens::hash(&config.tokens.secret, &token);
+    let Ok(codes) = sqlx::query!(
+        "SELECT id, code, created_at, valid_until, uses, max_uses FROM codes
+        WHERE created_by = (SELECT user_id FROM tokens WHERE token_hash = ?) AND invited_email IS NULL
+        ORDER BY created_at DESC",
+        token
+    )
+    .fetch_all(&pool)
+    .await
+    else {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    };
+
+    let Ok(redemptions) = sqlx::query!(
+        "SELECT code_id, email FROM code_redemptions
+        INNER JOIN users ON code_redemptions.user_id = users.id
+        INNER JOIN codes ON code_redemptions.code_id = codes.id
+        WHERE created_by = (SELECT user_id FROM tokens WHERE token_hash = ?) AND invited_email IS NULL
+        ORDER BY redeemed_at",
+        token
+    )
+    .fetch_all(&pool)
+    .await
+    else {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    };
+
+    let remaining = (config.invites.per_user - codes.len() as i64).max(0);
+    let invites = codes
+        .into_iter()
+        .map(|code| InviteResponse {
+            id: code.id,
+            code: code.code,
+            created_at: code.created_at,
+            valid_until: code.valid_until,
+            uses: code.uses,
+            max_uses: code.max_uses,
+            redeemed_by: redemptions
+                .iter()
+                .filter(|redemption| redemption.code_id == code.id)
+                .map(|redemption| redemption.email.clone())
+                .collect(),
+        })
+        .collect();
+
+    Ok(Json(InvitesResponse { remaining, invites }))
+}
+
+pub async fn create_invite(
+    headers: HeaderMap,
+    State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
+) -> impl IntoResponse {
+    if !config.features.use_registration_codes {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(String::from("Registration is open, no invite is needed")),
+        ));
+    }
+
+    let token = headers
+        .get(AUTHORIZATION)
+        .ok_or((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(String::from("Could not get token")),
+        ))?
+        .to_str()
+        .or(Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(String::from("Could not get token")),
+        )))?
+        .replace("Bearer ", "");
+
+    let token = tokens::hash(&config.tokens.secret, &token);
+    let Ok(user) = sqlx::query!(
+        "SELECT users.id, verified as `verified: bool` FROM users
+        INNER JOIN tokens ON tokens.user_id = users.id
+        WHERE token_hash = ? LIMIT 1",
+        token
+    )
+    .fetch_one(&pool)
+    .await
+    else {
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(String::from("Could not get user")),
+        ));
+    };
+
+    if !user.verified {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(String::from("Confirm your email before inviting others")),
+        ));
+    }
+
+    // Every invite ever created counts, so revoking or using one up does not free its slot
+    let Ok(created) = sqlx::query!(
+        "SELECT COUNT(id) as `count: i64` FROM codes WHERE created_by = ? AND invited_email IS NULL",
+        user.id
+    )
+    .fetch_one(&pool)
+    .await
+    else {
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(String::from("Could not count invites")),
+        ));
+    };
+
+    if created.count >= config.invites.per_user {
+        return Err((StatusCode::CONFLICT, Json(String::from("No invites left"))));
+    }
+
+    let mut bytes = [0u8; 12];
+    OsRng.fill_bytes(&mut bytes);
+
+    let code = URL_SAFE_NO_PAD.encode(bytes);
+    let now = Utc::now().naive_utc();
+    let valid_until = now + Duration::days(config.invites.lifetime_days);
+
+    let Ok(result) = sqlx::query!(
+        "INSERT INTO codes (code, created_at, valid_until, max_uses, uses, created_by) VALUES (?, ?, ?, ?, ?, ?)",
+        code,
+        now,
+        valid_until,
+        config.invites.max_uses,
+        0,
+        user.id
+    )
+    .execute(&pool)
+    .await
+    else {
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(String::from("Could not create invite")),
+        ));
+    };
+
+    Ok(Json(InviteResponse {
+        id: result.last_insert_id() as i32,
+        code,
+        created_at: Some(now),
+        valid_until: Some(valid_until),
+        uses: 0,
+        max_uses: Some(config.invites.max_uses),
+        redeemed_by: Vec::new(),
+    }))
+}
+
+/// Expires an invite right away. It still counts towards the quota
+pub async fn revoke_invite(
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+    State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
+) -> impl IntoResponse {
+    let token = headers
+        .get(AUTHORIZATION)
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?
+        .to_str()
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?
+        .replace("Bearer ", "");
+
+    let token = tokens::hash(&config.tokens.secret, &token);
+    let result = sqlx::query!(
+        "UPDATE codes SET valid_until = ? WHERE id = ? AND created_by = (SELECT user_id FROM tokens WHERE token_hash = ?)",
+        Utc::now().naive_utc(),
+        id,
+        token
+    )
+    .execute(&pool)
+    .await
+    .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(Json("Invite revoked"))
+}
@@ -1,15 +1,26 @@
+use crate::config::Config;
+use crate::http::client_ip::ClientIp;
+use crate::http::login_attempts::LoginAttempts;
+use crate::http::two_factor;
+use crate::tokens;
 use argon2::password_hash::rand_core;
 use argon2::password_hash::rand_core::RngCore;
 use argon2::{Argon2, PasswordHash, PasswordVerifier};
 use axum::Json;
 use axum::extract::State;
 use axum::http::StatusCode;
+use axum::http::header::RETRY_AFTER;
 use axum::response::IntoResponse;
 use axum_serde::macros::Deserialize;
 use base64::{Engine as _, engine::general_purpose::URL_SAFE};
 use chrono::{Duration, Utc};
 use serde_json::json;
 use sqlx::{MySql, Pool};
+use std::net::IpAddr;
+use std::sync::Arc;
+
+const TWO_FACTOR_CHALLENGE_LIFETIME_MINUTES: i64 = 5;
+const TWO_FACTOR_CHALLENGE_MAX_FAILURES: i32 = 5;
 
 #[derive(Deserialize)]
 pub struct Login {
@@ -17,62 +28,238 @@ pub struct Login {
     password: String,
 }
 
+#[derive(Deserialize)]
+pub struct LoginTwoFactor {
+    two_factor_token: String,
+    code: String,
+}
+
 pub async fn login(
     State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
+    State(login_attempts): State<LoginAttempts>,
+    ClientIp(ip): ClientIp,
     Json(payload): Json<Login>,
 ) -> impl IntoResponse {
+    if let Err(retry_after) = login_attempts.attempt(&payload.email, ip) {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            [(RETRY_AFTER, retry_after.to_string())],
+            Json(String::from("Too many failed attempts, try again later")),
+        )
+            .into_response());
+    }
+
     let Ok(user) = sqlx::query!(
-        "SELECT id, password FROM users WHERE email = ? LIMIT 1",
+        "SELECT id, password, totp_enabled as `totp_enabled: bool` FROM users WHERE email = ? LIMIT 1",
         payload.email
     )
     .fetch_one(&pool)
     .await
     else {
+        login_attempts
+            .record_failure(&pool, &payload.email, ip, "login")
+            .await;
+
         return Err((
             StatusCode::UNAUTHORIZED,
             Json(String::from("User not registered")),
-        ));
+        )
+            .into_response());
     };
 
     let Ok(parsed_hash) = PasswordHash::new(&user.password) else {
         return Err((
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(String::from("Error hashing password")),
-        ));
+        )
+            .into_response());
     };
 
     if Argon2::default()
         .verify_password(payload.password.as_bytes(), &parsed_hash)
         .is_ok()
     {
-        let mut bytes = [0u8; 88];
-        rand_core::OsRng.fill_bytes(&mut bytes);
-
-        let generated_token = URL_SAFE.encode(bytes);
-        let now = Utc::now().naive_utc();
-        let datetime = now + Duration::hours(24);
-
-        if sqlx::query!(
-            "INSERT INTO tokens (token, valid_until, user_id) VALUES (?, ?, ?)",
-            generated_token,
-            datetime,
-            user.id
-        )
-        .execute(&pool)
-        .await
-        .is_err()
-        {
+        // The bearer token is only issued by the second step, which also clears the failed attempts
+        if user.totp_enabled {
+            let mut bytes = [0u8; 32];
+            rand_core::OsRng.fill_bytes(&mut bytes);
+
+            let two_factor_token = URL_SAFE.encode(bytes);
+            let valid_until =
+                Utc::now().naive_utc() + Duration::minutes(TWO_FACTOR_CHALLENGE_LIFETIME_MINUTES);
+
+            if sqlx::query!(
+                "INSERT INTO two_factor_challenges (token_hash, valid_until, user_id) VALUES (?, ?, ?)",
+                tokens::hash(&config.tokens.secret, &two_factor_token),
+                valid_until,
+                user.id
+            )
+            .execute(&pool)
+            .await
+            .is_err()
+            {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(String::from("Error creating two-factor challenge")),
+                )
+                    .into_response());
+            }
+
+            return Ok((
+                StatusCode::OK,
+                Json(json!({"two_factor_token": two_factor_token})),
+            ));
+        }
+
+        let Ok(generated_token) = create_token(&pool, &config, ip, user.id).await else {
             return Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(String::from("Error creating new token")),
-            ));
-        }
+            )
+                .into_response());
+        };
 
+        login_attempts.record_success(&payload.email, ip);
         Ok((StatusCode::OK, Json(json!({"token": generated_token}))))
     } else {
+        login_attempts
+            .record_failure(&pool, &payload.email, ip, "login")
+            .await;
+
         Err((
             StatusCode::UNAUTHORIZED,
             Json(String::from("Email or password incorrect")),
-        ))
+        )
+            .into_response())
     }
 }
+
+pub async fn login_two_factor(
+    State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
+    State(login_attempts): State<LoginAttempts>,
+    ClientIp(ip): ClientIp,
+    Json(payload): Json<LoginTwoFactor>,
+) -> impl IntoResponse {
+    let Ok(challenge) = sqlx::query!(
+        "SELECT two_factor_challenges.id, user_id, email, totp_secret FROM two_factor_challenges
+        INNER JOIN users ON two_factor_challenges.user_id = users.id
+        WHERE token_hash = ? AND valid_until >= ? AND failed_attempts < ? LIMIT 1",
+        tokens::hash(&config.tokens.secret, &payload.two_factor_token),
+        Utc::now().naive_utc(),
+        TWO_FACTOR_CHALLENGE_MAX_FAILURES
+    )
+    .fetch_one(&pool)
+    .await
+    else {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(String::from("Sign in again")),
+        )
+            .into_response());
+    };
+
+    if let Err(retry_after) = login_attempts.attempt(&challenge.email, ip) {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            [(RETRY_AFTER, retry_after.to_string())],
+            Json(String::from("Too many failed attempts, try again later")),
+        )
+            .into_response());
+    }
+
+    let Some(secret) = challenge.totp_secret else {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(String::from("Sign in again")),
+        )
+            .into_response());
+    };
+
+    if !two_factor::verify(
+        &pool,
+        &config,
+        challenge.user_id,
+        &challenge.email,
+        &secret,
+        &payload.code,
+    )
+    .await
+    {
+        login_attempts
+            .record_failure(&pool, &challenge.email, ip, "login_two_factor")
+            .await;
+
+        // Too many wrong codes use the challenge up, so guessing needs the password again
+        let _ = sqlx::query!(
+            "UPDATE two_factor_challenges SET failed_attempts = failed_attempts + 1 WHERE id = ?",
+            challenge.id
+        )
+        .execute(&pool)
+        .await;
+
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(String::from("Code incorrect")),
+        )
+            .into_response());
+    }
+
+    login_attempts.record_success(&challenge.email, ip);
+
+    // Challenges are single use
+    if !sqlx::query!(
+        "DELETE FROM two_factor_challenges WHERE id = ?",
+        challenge.id
+    )
+    .execute(&pool)
+    .await
+    .is_ok_and(|result| result.rows_affected() == 1)
+    {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(String::from("Sign in again")),
+        )
+            .into_response());
+    }
+
+    let Ok(generated_token) = create_token(&pool, &config, ip, challenge.user_id).await else {
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(String::from("Error creating new token")),
+        )
+            .into_response());
+    };
+
+    Ok((StatusCode::OK, Json(json!({"token": generated_token}))))
+}
+
+async fn create_token(
+    pool: &Pool<MySql>,
+    config: &Config,
+    ip: IpAddr,
+    user_id: i32,
+) -> Result<String, sqlx::Error> {
+    let mut bytes = [0u8; 88];
+    rand_core::OsRng.fill_bytes(&mut bytes);
+
+    let generated_token = URL_SAFE.encode(bytes);
+    let now = Utc::now().naive_utc();
+    let datetime = now + Duration::hours(config.tokens.lifetime_hours);
+
+    sqlx::query!(
+        "INSERT INTO tokens (token_hash, valid_until, created_at, source, client_type, user_id)
+        VALUES (?, ?, ?, ?, ?, ?)",
+        tokens::hash(&config.tokens.secret, &generated_token),
+        datetime,
+        now,
+        ip.to_string(),
+        "web",
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(generated_token)
+}
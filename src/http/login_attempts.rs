@@ -0,0 +1,135 @@
+use crate::config::LoginAttemptsConfig;
+use chrono::Utc;
+use log::{error, info, warn};
+use sqlx::{MySql, Pool};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Failed password attempts per account and source IP, shared by every login endpoint
+#[derive(Clone)]
+pub struct LoginAttempts {
+    config: LoginAttemptsConfig,
+    attempts: Arc<Mutex<HashMap<(String, IpAddr), Attempt>>>,
+}
+
+struct Attempt {
+    failures: u32,
+    last_failure: Instant,
+    blocked_until: Instant,
+}
+
+impl LoginAttempts {
+    pub fn new(config: LoginAttemptsConfig) -> Self {
+        LoginAttempts {
+            config,
+            attempts: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Counts a login attempt as failed up front, so parallel requests can't all get past the backoff.
+    /// `record_success` clears it again. Returns the seconds left if this account is blocked for this IP.
+    pub fn attempt(&self, email: &str, ip: IpAddr) -> Result<(), u64> {
+        let Ok(mut attempts) = self.attempts.lock() else {
+            return Err(self.config.lockout_minutes * 60);
+        };
+
+        let now = Instant::now();
+        let forget_after = Duration::from_secs(self.config.lockout_minutes * 60);
+        attempts.retain(|_, attempt| now.duration_since(attempt.last_failure) < forget_after);
+
+        let attempt = attempts
+            .entry((email.to_lowercase(), ip))
+            .or_insert(Attempt {
+                failures: 0,
+                last_failure: now,
+                blocked_until: now,
+            });
+
+        let remaining = attempt.blocked_until.saturating_duration_since(now);
+        if !remaining.is_zero() {
+            return Err(remaining.as_secs() + 1);
+        }
+
+        attempt.failures += 1;
+        attempt.last_failure = now;
+        attempt.blocked_until = now + self.block_duration(attempt.failures);
+
+        if attempt.failures == self.config.lockout_attempts {
+            warn!(
+                "Locked out {email} from {ip} after {} failed logins",
+                attempt.failures
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Logs a failed attempt, which `attempt` has already counted
+    pub async fn record_failure(
+        &self,
+        pool: &Pool<MySql>,
+        email: &str,
+        ip: IpAddr,
+        endpoint: &str,
+    ) {
+        if let Err(error) = sqlx::query!(
+            "INSERT INTO failed_logins (email, ip_address, endpoint, attempted_at) VALUES (?, ?, ?, ?)",
+            email,
+            ip.to_string(),
+            endpoint,
+            Utc::now().naive_utc()
+        )
+        .execute(pool)
+        .await
+        {
+            error!("Could not record failed login for {email}: {error}");
+        }
+    }
+
+    pub fn record_success(&self, email: &str, ip: IpAddr) {
+        if let Ok(mut attempts) = self.attempts.lock() {
+            attempts.remove(&(email.to_lowercase(), ip));
+        }
+    }
+
+    fn block_duration(&self, failures: u32) -> Duration {
+        if failures >= self.config.lockout_attempts {
+            return Duration::from_secs(self.config.lockout_minutes * 60);
+        }
+
+        let Some(exponent) = failures.checked_sub(self.config.free_attempts) else {
+            return Duration::ZERO;
+        };
+
+        let backoff = self
+            .config
+            .backoff_seconds
+            .saturating_mul(2u64.saturating_pow(exponent));
+
+        Duration::from_secs(backoff.min(self.config.max_backoff_seconds))
+    }
+}
+
+/// Deletes failed logins older than the audit retention every hour
+pub async fn purge_failed_logins(pool: Pool<MySql>, config: LoginAttemptsConfig) {
+    let mut interval = tokio::time::interval(PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let cutoff = Utc::now().naive_utc() - chrono::Duration::days(config.audit_retention_days);
+
+        match sqlx::query!("DELETE FROM failed_logins WHERE attempted_at < ?", cutoff)
+            .execute(&pool)
+            .await
+        {
+            Ok(result) if result.rows_affected() > 0 => {
+                info!("Purged {} old failed logins", result.rows_affected());
+            }
+            Ok(_) => (),
+            Err(error) => error!("Could not purge old failed logins: {error}"),
+        }
+    }
+}
@@ -1,11 +1,15 @@
+use crate::config::Config;
+use crate::tokens;
 use axum::extract::State;
 use axum::http::header::AUTHORIZATION;
 use axum::http::{HeaderMap, StatusCode};
 use sqlx::{MySql, Pool};
+use std::sync::Arc;
 
 pub async fn logout(
     headers: HeaderMap,
     State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
 ) -> Result<StatusCode, StatusCode> {
     let token = headers
         .get(AUTHORIZATION)
@@ -14,7 +18,9 @@ pub async fn logout(
         .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?
         .replace("Bearer ", "");
 
-    sqlx::query!("DELETE FROM tokens WHERE token = ?", token)
+    let token = tokens::hash(&config.tokens.secret, &token);
+
+    sqlx::query!("DELETE FROM tokens WHERE token_hash = ?", token)
         .execute(&pool)
         .await
         .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
@@ -1,3 +1,5 @@
+use crate::config::Config;
+use crate::tokens;
 use axum::Json;
 use axum::extract::{Request, State};
 use axum::http::StatusCode;
@@ -6,9 +8,11 @@ use axum::middleware::Next;
 use axum::response::IntoResponse;
 use chrono::Utc;
 use sqlx::{MySql, Pool};
+use std::sync::Arc;
 
 pub async fn authentication(
     State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
     request: Request,
     next: Next,
 ) -> impl IntoResponse {
@@ -22,8 +26,11 @@ pub async fn authentication(
             )))?
             .replace("Bearer ", "");
 
+        let token = tokens::hash(&config.tokens.secret, &token);
+
+        // Tokens made for MSN clients can come from an app password, which must not reach the account settings
         let token = sqlx::query!(
-            "SELECT token, valid_until, user_id FROM tokens WHERE token = ? LIMIT 1",
+            "SELECT valid_until, user_id FROM tokens WHERE token_hash = ? AND client_type = 'web' LIMIT 1",
             token
         )
         .fetch_one(&pool)
@@ -1,5 +1,12 @@
+use crate::config::Config;
+use crate::http::app_state::AppState;
+use crate::http::login_attempts::LoginAttempts;
 use crate::http::middleware::authentication;
+use crate::http::reset_limiter::ResetLimiter;
+use crate::limits::user_limiter::UserLimiter;
+use crate::mail::mailer::Mailer;
 use crate::message::Message;
+use axum::extract::ConnectInfo;
 use axum::routing::delete;
 use axum::{
     Router,
@@ -13,50 +20,118 @@ use hyper_util::{
 };
 use log::{error, info};
 use sqlx::{MySql, Pool};
-use std::env;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::broadcast;
 use tower_http::cors::CorsLayer;
 use tower_service::Service;
 
+mod app_passwords;
+mod app_state;
 mod change_email;
 mod change_password;
+mod client_ip;
+mod confirm_email;
 mod delete_account;
+mod email_confirmation;
+mod export;
+mod forgot_password;
+mod import;
+mod invites;
 mod login;
+mod login_attempts;
 mod logout;
 mod middleware;
 mod nexus;
 mod passport_one_four;
+mod pending_contacts;
+mod profile;
 mod register;
+mod reset_limiter;
+mod reset_password;
 mod rst;
+mod sessions;
 mod stats;
+mod two_factor;
 mod user;
 mod xml;
 
 /// Starts the HTTP server with hyper so headers can be served with title case
-pub async fn listen(pool: Pool<MySql>, broadcast_tx: broadcast::Sender<Message>) {
-    let frontend_url = env::var("FRONTEND_URL").expect("FRONTEND_URL not set");
+pub async fn listen(
+    pool: Pool<MySql>,
+    broadcast_tx: broadcast::Sender<Message>,
+    config: Arc<Config>,
+    mailer: Mailer,
+) {
     let cors = CorsLayer::new().allow_origin(
-        frontend_url
+        config
+            .http
+            .frontend_url
             .parse::<HeaderValue>()
-            .expect("Could not convert FRONTEND_URL to header"),
+            .expect("Could not convert frontend URL to header"),
     );
 
+    tokio::spawn(login_attempts::purge_failed_logins(
+        pool.clone(),
+        config.login_attempts.clone(),
+    ));
+
+    let state = AppState {
+        pool,
+        broadcast_tx,
+        config: config.clone(),
+        login_attempts: LoginAttempts::new(config.login_attempts.clone()),
+        reset_limiter: ResetLimiter::new(&config.limits),
+        import_limiter: UserLimiter::new(
+            config.limits.imports_per_day,
+            Duration::from_secs(24 * 60 * 60),
+        ),
+        mailer,
+    };
+
     let authentication =
-        axum::middleware::from_fn_with_state(pool.clone(), authentication::authentication);
+        axum::middleware::from_fn_with_state(state.clone(), authentication::authentication);
 
     let user_routes = Router::new()
         .route("/", get(user::user))
         .route("/", delete(delete_account::delete_account))
+        .route("/restore", post(delete_account::restore_account))
         .route("/change-email", post(change_email::change_email))
         .route("/change-password", post(change_password::change_password))
         .route("/logout", post(logout::logout))
+        .route(
+            "/resend-confirmation",
+            post(confirm_email::resend_confirmation),
+        )
+        .route("/sessions", get(sessions::sessions))
+        .route("/sessions/{id}", delete(sessions::revoke_session))
+        .route("/app-passwords", get(app_passwords::app_passwords))
+        .route("/app-passwords", post(app_passwords::create_app_password))
+        .route(
+            "/app-passwords/{id}",
+            delete(app_passwords::revoke_app_password),
+        )
+        .route("/pending-contacts", get(pending_contacts::pending_contacts))
+        .route("/profile", get(profile::profile))
+        .route("/profile", post(profile::update_profile))
+        .route("/export", get(export::export))
+        .route("/import", post(import::import))
+        .route("/invites", get(invites::invites))
+        .route("/invites", post(invites::create_invite))
+        .route("/invites/{id}", delete(invites::revoke_invite))
+        .route("/two-factor/enroll", post(two_factor::enroll))
+        .route("/two-factor/enable", post(two_factor::enable))
+        .route("/two-factor/disable", post(two_factor::disable))
         .layer(authentication);
 
     let r2m_routes = Router::new()
         .route("/stats", get(stats::stats))
-        .with_state(broadcast_tx)
         .route("/register", post(register::register))
         .route("/login", post(login::login))
+        .route("/login/two-factor", post(login::login_two_factor))
+        .route("/forgot-password", post(forgot_password::forgot_password))
+        .route("/reset-password", post(reset_password::reset_password))
+        .route("/confirm-email", post(confirm_email::confirm_email))
         .nest("/user", user_routes)
         .layer(cors);
 
@@ -70,16 +145,16 @@ pub async fn listen(pool: Pool<MySql>, broadcast_tx: broadcast::Sender<Message>)
                 middleware::content_type_xml::content_type_xml,
             )),
         )
-        .with_state(pool);
+        .with_state(state);
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000")
+    let listener = tokio::net::TcpListener::bind(&config.http.bind_address)
         .await
         .expect("Could not bind HTTP server");
 
-    info!("HTTP server listening on port 3000");
+    info!("HTTP server listening on {}", config.http.bind_address);
 
     loop {
-        let (socket, _remote_addr) = match listener.accept().await {
+        let (socket, remote_address) = match listener.accept().await {
             Ok(listener) => listener,
             Err(error) => {
                 error!("Could not get socket from accepted HTTP connection: {error}");
@@ -90,9 +165,12 @@ pub async fn listen(pool: Pool<MySql>, broadcast_tx: broadcast::Sender<Message>)
         let tower_service = app.clone();
         tokio::spawn(async move {
             let socket = TokioIo::new(socket);
-            let hyper_service = hyper::service::service_fn(move |request: Request<Incoming>| {
-                tower_service.clone().call(request)
-            });
+            let hyper_service =
+                hyper::service::service_fn(move |mut request: Request<Incoming>| {
+                    request.extensions_mut().insert(ConnectInfo(remote_address));
+
+                    tower_service.clone().call(request)
+                });
 
             let mut builder = server::conn::auto::Builder::new(TokioExecutor::new());
             builder.http1().title_case_headers(true);
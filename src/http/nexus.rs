@@ -1,7 +1,9 @@
+use crate::config::Config;
+use axum::extract::State;
 use axum::response::IntoResponse;
-use std::env;
+use std::sync::Arc;
 
-pub async fn nexus() -> impl IntoResponse {
-    let server_name = env::var("SERVER_DOMAIN").expect("SERVER_DOMAIN not set");
+pub async fn nexus(State(config): State<Arc<Config>>) -> impl IntoResponse {
+    let server_name = &config.http.server_domain;
     [("PassportURLs", format!("DALogin={server_name}/login.srf"))]
 }
@@ -1,7 +1,9 @@
-use argon2::{
-    Argon2, PasswordHash, PasswordVerifier,
-    password_hash::{SaltString, rand_core},
-};
+use crate::config::Config;
+use crate::http::app_passwords;
+use crate::http::client_ip::ClientIp;
+use crate::http::login_attempts::LoginAttempts;
+use crate::tokens;
+use argon2::password_hash::{SaltString, rand_core};
 use axum::{
     extract::State,
     http::{HeaderMap, StatusCode, header},
@@ -10,6 +12,7 @@ use axum::{
 use chrono::{Duration, Utc};
 use log::trace;
 use sqlx::{MySql, Pool};
+use std::sync::Arc;
 
 enum HeaderParsingError {
     HeaderNotFound,
@@ -21,12 +24,15 @@ enum HeaderParsingError {
 pub async fn passport_one_four(
     headers: HeaderMap,
     State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
+    State(login_attempts): State<LoginAttempts>,
+    ClientIp(ip): ClientIp,
 ) -> impl IntoResponse {
     let authorization = headers
         .get(header::AUTHORIZATION)
         .ok_or(HeaderParsingError::HeaderNotFound)
         .and_then(|header| header.to_str().or(Err(HeaderParsingError::ToStrError)))
-        .or(Err(StatusCode::UNAUTHORIZED))?;
+        .or(Err(StatusCode::UNAUTHORIZED.into_response()))?;
 
     let passport = authorization
         .split("sign-in=")
@@ -41,7 +47,7 @@ pub async fn passport_one_four(
                     urlencoding::decode(passport).or(Err(HeaderParsingError::UrlDecodingError))
                 })
         })
-        .or(Err(StatusCode::UNAUTHORIZED))?;
+        .or(Err(StatusCode::UNAUTHORIZED.into_response()))?;
 
     let pwd = authorization
         .split("pwd=")
@@ -56,38 +62,61 @@ pub async fn passport_one_four(
                     urlencoding::decode(password).or(Err(HeaderParsingError::UrlDecodingError))
                 })
         })
-        .or(Err(StatusCode::UNAUTHORIZED))?;
+        .or(Err(StatusCode::UNAUTHORIZED.into_response()))?;
+
+    if let Err(retry_after) = login_attempts.attempt(&passport, ip) {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            [(header::RETRY_AFTER, retry_after.to_string())],
+        )
+            .into_response());
+    }
 
-    let user = sqlx::query!(
-        "SELECT id, email, password FROM users WHERE email = ? LIMIT 1",
+    let Ok(user) = sqlx::query!(
+        "SELECT id, email, password, totp_enabled as `totp_enabled: bool` FROM users
+        WHERE email = ? AND deletion_scheduled_at IS NULL LIMIT 1",
         passport
     )
     .fetch_one(&pool)
     .await
-    .or(Err(StatusCode::UNAUTHORIZED))?;
+    else {
+        login_attempts
+            .record_failure(&pool, &passport, ip, "login.srf")
+            .await;
 
-    let parsed_hash =
-        PasswordHash::new(&user.password).or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+        return Err(StatusCode::UNAUTHORIZED.into_response());
+    };
 
-    if Argon2::default()
-        .verify_password(pwd.as_bytes(), &parsed_hash)
-        .is_ok()
+    if app_passwords::verify_client_password(
+        &pool,
+        user.id,
+        &user.password,
+        user.totp_enabled,
+        &pwd,
+    )
+    .await
     {
+        login_attempts.record_success(&passport, ip);
+
         let mut generated_token =
             urlencoding::encode(SaltString::generate(&mut rand_core::OsRng).as_str()).to_string();
 
         generated_token.insert_str(0, "t=");
-        let datetime = (Utc::now() + Duration::hours(24)).naive_utc();
+        let datetime = (Utc::now() + Duration::hours(config.tokens.lifetime_hours)).naive_utc();
 
         sqlx::query!(
-            "INSERT INTO tokens (token, valid_until, user_id) VALUES (?, ?, ?)",
-            generated_token,
+            "INSERT INTO tokens (token_hash, valid_until, created_at, source, client_type, user_id)
+            VALUES (?, ?, ?, ?, ?, ?)",
+            tokens::hash(&config.tokens.secret, &generated_token),
             datetime,
+            Utc::now().naive_utc(),
+            ip.to_string(),
+            "passport",
             user.id
         )
         .execute(&pool)
         .await
-        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR.into_response()))?;
 
         trace!("Generated token for {}", user.email);
         return Ok([(
@@ -96,5 +125,9 @@ pub async fn passport_one_four(
         )]);
     }
 
-    Err(StatusCode::UNAUTHORIZED)
+    login_attempts
+        .record_failure(&pool, &passport, ip, "login.srf")
+        .await;
+
+    Err(StatusCode::UNAUTHORIZED.into_response())
 }
@@ -0,0 +1,59 @@
+use crate::config::Config;
+use crate::tokens;
+use axum::Json;
+use axum::extract::State;
+use axum::http::header::AUTHORIZATION;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum_serde::macros::Serialize;
+use chrono::NaiveDateTime;
+use sqlx::{MySql, Pool};
+use std::sync::Arc;
+
+#[derive(Serialize)]
+pub struct PendingContactResponse {
+    email: String,
+    display_name: String,
+    added_at: NaiveDateTime,
+}
+
+/// Users who added the signed in user and haven't been allowed or blocked yet
+pub async fn pending_contacts(
+    headers: HeaderMap,
+    State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
+) -> impl IntoResponse {
+    let token = headers
+        .get(AUTHORIZATION)
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?
+        .to_str()
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?
+        .replace("Bearer ", "");
+
+    let token = tokens::hash(&config.tokens.secret, &token);
+    let Ok(additions) = sqlx::query!(
+        "SELECT users.email, users.display_name, pending_additions.added_at FROM pending_additions
+        INNER JOIN users ON pending_additions.added_by = users.id
+        WHERE pending_additions.user_id = (SELECT user_id FROM tokens WHERE token_hash = ?)
+        ORDER BY pending_additions.added_at DESC",
+        token
+    )
+    .fetch_all(&pool)
+    .await
+    else {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    };
+
+    Ok(Json(
+        additions
+            .into_iter()
+            .map(|addition| PendingContactResponse {
+                display_name: urlencoding::decode(&addition.display_name)
+                    .map(|display_name| display_name.into_owned())
+                    .unwrap_or(addition.display_name),
+                email: addition.email,
+                added_at: addition.added_at,
+            })
+            .collect::<Vec<PendingContactResponse>>(),
+    ))
+}
@@ -0,0 +1,155 @@
+use crate::config::Config;
+use crate::tokens;
+use axum::Json;
+use axum::extract::State;
+use axum::http::header::AUTHORIZATION;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum_serde::macros::{Deserialize, Serialize};
+use sqlx::{MySql, Pool};
+use std::sync::Arc;
+
+const MAX_FIELD_LENGTH: usize = 64;
+
+#[derive(Serialize, Deserialize)]
+pub struct Profile {
+    first_name: String,
+    last_name: String,
+    city: String,
+    state: String,
+    country: String,
+    listed: bool,
+}
+
+pub async fn profile(
+    headers: HeaderMap,
+    State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
+) -> impl IntoResponse {
+    let token = headers
+        .get(AUTHORIZATION)
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?
+        .to_str()
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?
+        .replace("Bearer ", "");
+
+    let token = tokens::hash(&config.tokens.secret, &token);
+    let Ok(profile) = sqlx::query!(
+        "SELECT first_name, last_name, city, state, country, listed as `listed: bool`
+        FROM profiles INNER JOIN tokens ON tokens.user_id = profiles.user_id
+        WHERE token_hash = ? LIMIT 1",
+        token
+    )
+    .fetch_optional(&pool)
+    .await
+    else {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    };
+
+    // Users who never saved a profile aren't listed
+    let Some(profile) = profile else {
+        return Ok(Json(Profile {
+            first_name: String::new(),
+            last_name: String::new(),
+            city: String::new(),
+            state: String::new(),
+            country: String::new(),
+            listed: false,
+        }));
+    };
+
+    Ok(Json(Profile {
+        first_name: profile.first_name,
+        last_name: profile.last_name,
+        city: profile.city,
+        state: profile.state,
+        country: profile.country,
+        listed: profile.listed,
+    }))
+}
+
+pub async fn update_profile(
+    headers: HeaderMap,
+    State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
+    Json(payload): Json<Profile>,
+) -> impl IntoResponse {
+    let fields = [
+        &payload.first_name,
+        &payload.last_name,
+        &payload.city,
+        &payload.state,
+        &payload.country,
+    ];
+
+    if fields.iter().any(|field| {
+        field.chars().count() > MAX_FIELD_LENGTH
+            || field.chars().any(char::is_control)
+            || field.trim() != field.as_str()
+    }) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(String::from(
+                "Profile fields can be at most 64 characters long, without surrounding spaces",
+            )),
+        ));
+    }
+
+    if payload.listed && (payload.first_name.is_empty() || payload.last_name.is_empty()) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(String::from(
+                "A first and last name are needed to be listed in the member directory",
+            )),
+        ));
+    }
+
+    let token = headers
+        .get(AUTHORIZATION)
+        .ok_or((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(String::from("Could not get token")),
+        ))?
+        .to_str()
+        .or(Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(String::from("Could not get token")),
+        )))?
+        .replace("Bearer ", "");
+
+    let token = tokens::hash(&config.tokens.secret, &token);
+    let Ok(user) = sqlx::query!(
+        "SELECT user_id FROM tokens WHERE token_hash = ? LIMIT 1",
+        token
+    )
+    .fetch_one(&pool)
+    .await
+    else {
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(String::from("User not found")),
+        ));
+    };
+
+    sqlx::query!(
+        "INSERT INTO profiles (user_id, first_name, last_name, city, state, country, listed)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        ON DUPLICATE KEY UPDATE first_name = VALUES(first_name), last_name = VALUES(last_name),
+        city = VALUES(city), state = VALUES(state), country = VALUES(country), listed = VALUES(listed)",
+        user.user_id,
+        payload.first_name,
+        payload.last_name,
+        payload.city,
+        payload.state,
+        payload.country,
+        payload.listed
+    )
+    .execute(&pool)
+    .await
+    .or(Err((
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(String::from("Could not save profile")),
+    )))?;
+
+    Ok(Json(payload))
+}
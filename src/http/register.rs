@@ -1,3 +1,7 @@
+use crate::config::Config;
+use crate::http::email_confirmation;
+use crate::mail::mailer::Mailer;
+use crate::message::Message;
 use argon2::{
     Argon2, PasswordHasher,
     password_hash::{
@@ -7,11 +11,13 @@ use argon2::{
 };
 use axum::response::IntoResponse;
 use axum::{Json, extract::State, http::StatusCode};
+use chrono::Utc;
 use email_address::EmailAddress;
 use log::trace;
 use serde::Deserialize;
-use sqlx::{MySql, Pool};
-use std::env;
+use sqlx::{MySql, Pool, Transaction};
+use std::sync::Arc;
+use tokio::sync::broadcast;
 
 #[derive(Deserialize)]
 pub struct CreateUser {
@@ -23,6 +29,9 @@ pub struct CreateUser {
 
 pub async fn register(
     State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
+    State(mailer): State<Mailer>,
+    State(broadcast_tx): State<broadcast::Sender<Message>>,
     Json(payload): Json<CreateUser>,
 ) -> impl IntoResponse {
     if payload.password.len() < 8 {
@@ -52,24 +61,52 @@ pub async fn register(
         .is_ok()
     {
         return (
-            StatusCode::BAD_REQUEST,
+            StatusCode::CONFLICT,
             Json(String::from("User already registered")),
         );
     }
 
-    if env::var("USE_REGISTRATION_CODES")
-        .map(|var| var.parse::<bool>().unwrap_or(true))
-        .unwrap_or(true)
-        && sqlx::query!("SELECT id FROM codes WHERE code = ?", payload.code)
-            .fetch_one(&pool)
-            .await
-            .is_err()
-    {
-        return (
-            StatusCode::UNAUTHORIZED,
-            Json(String::from("Code not found")),
-        );
-    }
+    // Invitation codes also work when registration is open, to add the inviter
+    let code = if config.features.use_registration_codes || !payload.code.is_empty() {
+        let Ok(code) = sqlx::query!(
+            "SELECT id, created_by, invited_email FROM codes WHERE code = ? AND (valid_until IS NULL OR valid_until >= ?)
+            AND (max_uses IS NULL OR uses < max_uses) LIMIT 1",
+            payload.code,
+            Utc::now().naive_utc()
+        )
+        .fetch_one(&pool)
+        .await
+        else {
+            return (
+                StatusCode::UNAUTHORIZED,
+                Json(String::from("Code not found, expired or used up")),
+            );
+        };
+
+        if code
+            .invited_email
+            .as_ref()
+            .is_some_and(|invited_email| !invited_email.eq_ignore_ascii_case(&payload.email))
+        {
+            return (
+                StatusCode::UNAUTHORIZED,
+                Json(String::from(
+                    "This invitation was sent to another email address",
+                )),
+            );
+        }
+
+        Some(code)
+    } else {
+        None
+    };
+
+    let code_id = code.as_ref().map(|code| code.id);
+
+    // Following the link in an invitation mail already proves the address is theirs
+    let inviter_id = code
+        .filter(|code| code.invited_email.is_some())
+        .and_then(|code| code.created_by);
 
     let salt = SaltString::generate(&mut OsRng);
     let argon2 = Argon2::default();
@@ -85,28 +122,172 @@ pub async fn register(
     let passport_id = OsRng.next_u64();
     let user_guid = guid_create::GUID::rand().to_string().to_lowercase();
 
-    if sqlx::query!(
-        "INSERT INTO users (email, password, display_name, puid, guid, gtc, blp) VALUES (?, ?, ?, ?, ?, ?, ?)", 
+    let verified = !config.features.require_email_verification || inviter_id.is_some();
+
+    let Ok(mut transaction) = pool.begin().await else {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(String::from("Could not register user")),
+        );
+    };
+
+    // Checked again while consuming it, in case the last use was taken or it was revoked in the meantime
+    if let Some(code_id) = code_id
+        && !sqlx::query!(
+            "UPDATE codes SET uses = uses + 1 WHERE id = ? AND (max_uses IS NULL OR uses < max_uses)
+            AND (valid_until IS NULL OR valid_until > ?)",
+            code_id,
+            Utc::now().naive_utc()
+        )
+        .execute(&mut *transaction)
+        .await
+        .is_ok_and(|result| result.rows_affected() == 1)
+    {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(String::from("Code not found, expired or used up")),
+        );
+    }
+
+    let result = match sqlx::query!(
+        "INSERT INTO users (email, password, display_name, puid, guid, gtc, blp, verified) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
         payload.email,
         password_hash,
         payload.email,
         passport_id,
         user_guid,
         "A",
-        "AL"
+        "AL",
+        verified
     )
-        .execute(&pool)
+    .execute(&mut *transaction)
+    .await
+    {
+        Ok(result) => result,
+        // Another registration with the same email got in first
+        Err(sqlx::Error::Database(error)) if error.is_unique_violation() => {
+            return (
+                StatusCode::CONFLICT,
+                Json(String::from("User already registered")),
+            );
+        }
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(String::from("Could not register user")),
+            );
+        }
+    };
+
+    let user_id = result.last_insert_id() as i32;
+    if let Some(code_id) = code_id
+        && sqlx::query!(
+            "INSERT INTO code_redemptions (redeemed_at, code_id, user_id) VALUES (?, ?, ?)",
+            Utc::now().naive_utc(),
+            code_id,
+            user_id
+        )
+        .execute(&mut *transaction)
         .await
-        .is_err() {
+        .is_err()
+    {
         return (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(String::from("Could not register user")),
         );
     }
 
+    let mut inviter_email = None;
+    if let Some(inviter_id) = inviter_id {
+        let Ok(email) = add_inviter(&mut transaction, inviter_id, user_id, &payload.email).await
+        else {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(String::from("Could not register user")),
+            );
+        };
+
+        inviter_email = Some(email);
+    }
+
+    if transaction.commit().await.is_err() {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(String::from("Could not register user")),
+        );
+    }
+
+    // A signed in inviter sees the new user added to their lists, like any other add
+    if let Some(inviter_email) = inviter_email {
+        let _ = broadcast_tx.send(Message::ToContact {
+            sender: Arc::new(payload.email.clone()),
+            receiver: Arc::new(inviter_email),
+            message: format!("AddContact {user_guid}\r\n"),
+        });
+    }
+
     trace!("{} registered", payload.email);
+    if verified {
+        return (
+            StatusCode::OK,
+            Json(String::from("User created successfully")),
+        );
+    }
+
+    if email_confirmation::send(&pool, &config, mailer, user_id, payload.email)
+        .await
+        .is_err()
+    {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(String::from(
+                "User created, but the confirmation link could not be sent",
+            )),
+        );
+    }
+
     (
         StatusCode::OK,
-        Json(String::from("User created successfully")),
+        Json(String::from(
+            "User created successfully, check your email to confirm it",
+        )),
     )
 }
+
+/// Puts the new user and whoever invited them on each other's forward and allow lists, returns the inviter's email
+async fn add_inviter(
+    transaction: &mut Transaction<'_, MySql>,
+    inviter_id: i32,
+    user_id: i32,
+    email: &str,
+) -> Result<String, sqlx::Error> {
+    let inviter = sqlx::query!(
+        "SELECT email, display_name FROM users WHERE id = ?",
+        inviter_id
+    )
+    .fetch_one(&mut **transaction)
+    .await?;
+
+    sqlx::query!(
+        "INSERT INTO contacts (user_id, contact_id, display_name, in_forward_list, in_allow_list, in_block_list)
+        VALUES (?, ?, ?, TRUE, TRUE, FALSE), (?, ?, ?, TRUE, TRUE, FALSE)",
+        inviter_id,
+        user_id,
+        email,
+        user_id,
+        inviter_id,
+        inviter.display_name
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    sqlx::query!(
+        "UPDATE users SET list_version = list_version + 1, list_updated_at = ? WHERE id = ?",
+        Utc::now().naive_utc(),
+        inviter_id
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(inviter.email)
+}
@@ -0,0 +1,28 @@
+use crate::config::LimitsConfig;
+use crate::limits::user_limiter::UserLimiter;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+const WINDOW: Duration = Duration::from_secs(60 * 60);
+
+/// Password reset requests per address and per source IP, so reset mail can't be used to flood inboxes
+#[derive(Clone)]
+pub struct ResetLimiter {
+    by_email: UserLimiter,
+    by_ip: UserLimiter,
+}
+
+impl ResetLimiter {
+    pub fn new(config: &LimitsConfig) -> Self {
+        ResetLimiter {
+            by_email: UserLimiter::new(config.password_resets_per_email, WINDOW),
+            by_ip: UserLimiter::new(config.password_resets_per_ip, WINDOW),
+        }
+    }
+
+    pub fn try_take(&self, email: &str, ip: IpAddr) -> bool {
+        self.by_ip.try_take(&Arc::new(ip.to_string()))
+            && self.by_email.try_take(&Arc::new(email.to_lowercase()))
+    }
+}
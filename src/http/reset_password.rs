@@ -0,0 +1,119 @@
+use crate::config::Config;
+use crate::message::Message;
+use crate::tokens;
+use argon2::password_hash::SaltString;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::{Argon2, PasswordHasher};
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use chrono::Utc;
+use serde::Deserialize;
+use sqlx::{MySql, Pool};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+#[derive(Deserialize)]
+pub struct ResetPassword {
+    token: String,
+    new_password: String,
+}
+
+pub async fn reset_password(
+    State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
+    State(broadcast_tx): State<broadcast::Sender<Message>>,
+    Json(payload): Json<ResetPassword>,
+) -> impl IntoResponse {
+    if payload.new_password.len() < 8 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(String::from(
+                "New password must be at least 8 characters long",
+            )),
+        ));
+    }
+
+    let Ok(reset) = sqlx::query!(
+        "SELECT password_resets.id, user_id, users.email FROM password_resets
+        INNER JOIN users ON password_resets.user_id = users.id
+        WHERE token_hash = ? AND valid_until >= ? LIMIT 1",
+        tokens::hash(&config.tokens.secret, &payload.token),
+        Utc::now().naive_utc()
+    )
+    .fetch_one(&pool)
+    .await
+    else {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(String::from("Reset link invalid or expired")),
+        ));
+    };
+
+    let salt = SaltString::generate(&mut OsRng);
+    let Ok(password_hash) = Argon2::default().hash_password(payload.new_password.as_bytes(), &salt)
+    else {
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(String::from("Could not hash password")),
+        ));
+    };
+
+    let mut transaction = pool.begin().await.or(Err((
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(String::from("Could not change password")),
+    )))?;
+
+    // Deleting the reset first makes sure the link can only be used once, even with concurrent requests
+    let deleted = sqlx::query!("DELETE FROM password_resets WHERE id = ?", reset.id)
+        .execute(&mut *transaction)
+        .await
+        .or(Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(String::from("Could not change password")),
+        )))?;
+
+    if deleted.rows_affected() == 0 {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(String::from("Reset link invalid or expired")),
+        ));
+    }
+
+    sqlx::query!(
+        "UPDATE users SET password = ? WHERE id = ?",
+        password_hash.to_string(),
+        reset.user_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .or(Err((
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(String::from("Could not change password")),
+    )))?;
+
+    // Log out everywhere
+    sqlx::query!("DELETE FROM tokens WHERE user_id = ?", reset.user_id)
+        .execute(&mut *transaction)
+        .await
+        .or(Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(String::from("Could not log out")),
+        )))?;
+
+    transaction.commit().await.or(Err((
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(String::from("Could not change password")),
+    )))?;
+
+    // Clients that are still signed in with the old password are disconnected as well
+    let email = Arc::new(reset.email);
+    let _ = broadcast_tx.send(Message::ToContact {
+        sender: email.clone(),
+        receiver: email,
+        message: String::from("PasswordReset\r\n"),
+    });
+
+    Ok(Json("Password changed successfully"))
+}
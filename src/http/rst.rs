@@ -14,11 +14,15 @@ use super::xml::rst_xml::{
     wsu::{Created, Expires},
     xs,
 };
+use crate::config::Config;
+use crate::http::app_passwords;
+use crate::http::client_ip::ClientIp;
+use crate::http::login_attempts::LoginAttempts;
+use crate::tokens;
 use argon2::password_hash::{
     SaltString,
     rand_core::{self, RngCore},
 };
-use argon2::{Argon2, PasswordHash, PasswordVerifier};
 use axum::http::StatusCode;
 use axum::{extract::State, response::IntoResponse};
 use axum_serde::Xml;
@@ -27,6 +31,7 @@ use chrono::{Duration, Utc};
 use log::{error, trace};
 use quick_xml::events::{BytesDecl, Event};
 use sqlx::{MySql, Pool};
+use std::sync::Arc;
 
 enum ElementNotFoundError {
     Header,
@@ -40,6 +45,9 @@ enum ElementNotFoundError {
 
 pub async fn rst(
     State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
+    State(login_attempts): State<LoginAttempts>,
+    ClientIp(ip): ClientIp,
     Xml(envelope): Xml<Envelope>,
 ) -> impl IntoResponse {
     let Ok(username_token) = envelope
@@ -63,31 +71,45 @@ pub async fn rst(
         return invalid_request_envelope();
     };
 
+    let email = &username_token.username.content;
+    if login_attempts.attempt(email, ip).is_err() {
+        return failed_authentication_envelope();
+    }
+
     let Ok(user) = sqlx::query!(
-        "SELECT id, email, password, puid FROM users WHERE email = ? LIMIT 1",
-        username_token.username.content
+        "SELECT id, email, password, puid, totp_enabled as `totp_enabled: bool` FROM users
+        WHERE email = ? AND deletion_scheduled_at IS NULL LIMIT 1",
+        email
     )
     .fetch_one(&pool)
     .await
     else {
-        return invalid_request_envelope();
-    };
+        login_attempts
+            .record_failure(&pool, email, ip, "RST.srf")
+            .await;
 
-    let parsed_hash = match PasswordHash::new(&user.password) {
-        Ok(parsed_hash) => parsed_hash,
-        Err(error) => {
-            error!("Error hashing password: {error}");
-            return failed_authentication_envelope();
-        }
+        return invalid_request_envelope();
     };
 
-    if let Err(error) =
-        Argon2::default().verify_password(username_token.password.content.as_bytes(), &parsed_hash)
+    if !app_passwords::verify_client_password(
+        &pool,
+        user.id,
+        &user.password,
+        user.totp_enabled,
+        &username_token.password.content,
+    )
+    .await
     {
-        error!("Error verifying password: {error}");
+        trace!("Password incorrect for {email}");
+        login_attempts
+            .record_failure(&pool, email, ip, "RST.srf")
+            .await;
+
         return failed_authentication_envelope();
     }
 
+    login_attempts.record_success(email, ip);
+
     let binary_secret = SaltString::generate(&mut rand_core::OsRng)
         .as_str()
         .to_string();
@@ -99,12 +121,16 @@ pub async fn rst(
     generated_token.insert_str(0, "t=");
 
     let now = Utc::now().naive_utc();
-    let datetime = now + Duration::hours(24);
+    let datetime = now + Duration::hours(config.tokens.lifetime_hours);
 
     if sqlx::query!(
-        "INSERT INTO tokens (token, valid_until, user_id) VALUES (?, ?, ?)",
-        generated_token,
+        "INSERT INTO tokens (token_hash, valid_until, created_at, source, client_type, user_id)
+        VALUES (?, ?, ?, ?, ?, ?)",
+        tokens::hash(&config.tokens.secret, &generated_token),
         datetime,
+        now,
+        ip.to_string(),
+        "rst",
         user.id
     )
     .execute(&pool)
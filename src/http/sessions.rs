@@ -0,0 +1,114 @@
+use crate::config::Config;
+use crate::message::Message;
+use crate::tokens;
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::header::AUTHORIZATION;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum_serde::macros::Serialize;
+use chrono::{NaiveDateTime, Utc};
+use sqlx::{MySql, Pool};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+#[derive(Serialize)]
+pub struct SessionResponse {
+    id: i32,
+    created_at: NaiveDateTime,
+    valid_until: NaiveDateTime,
+    source: String,
+    client_type: String,
+    current: bool,
+}
+
+pub async fn sessions(
+    headers: HeaderMap,
+    State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
+) -> impl IntoResponse {
+    let token = headers
+        .get(AUTHORIZATION)
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?
+        .to_str()
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?
+        .replace("Bearer ", "");
+
+    let token = tokens::hash(&config.tokens.secret, &token);
+    let Ok(sessions) = sqlx::query!(
+        "SELECT id, token_hash, created_at, valid_until, source, client_type FROM tokens
+        WHERE user_id = (SELECT user_id FROM tokens WHERE token_hash = ?) AND valid_until >= ?
+        ORDER BY created_at DESC",
+        token,
+        Utc::now().naive_utc()
+    )
+    .fetch_all(&pool)
+    .await
+    else {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    };
+
+    Ok(Json(
+        sessions
+            .into_iter()
+            .map(|session| SessionResponse {
+                id: session.id,
+                created_at: session.created_at,
+                valid_until: session.valid_until,
+                source: session.source,
+                client_type: session.client_type,
+                current: session.token_hash == token,
+            })
+            .collect::<Vec<SessionResponse>>(),
+    ))
+}
+
+pub async fn revoke_session(
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+    State(pool): State<Pool<MySql>>,
+    State(broadcast_tx): State<broadcast::Sender<Message>>,
+    State(config): State<Arc<Config>>,
+) -> impl IntoResponse {
+    let token = headers
+        .get(AUTHORIZATION)
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?
+        .to_str()
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?
+        .replace("Bearer ", "");
+
+    let token = tokens::hash(&config.tokens.secret, &token);
+    let Ok(user) = sqlx::query!(
+        "SELECT users.id, email FROM users INNER JOIN tokens ON tokens.user_id = users.id
+        WHERE token_hash = ? LIMIT 1",
+        token
+    )
+    .fetch_one(&pool)
+    .await
+    else {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    };
+
+    let result = sqlx::query!(
+        "DELETE FROM tokens WHERE id = ? AND user_id = ?",
+        id,
+        user.id
+    )
+    .execute(&pool)
+    .await
+    .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    // Disconnects the Notification Server session that logged in with this token, if any
+    let email = Arc::new(user.email);
+    let _ = broadcast_tx.send(Message::ToContact {
+        sender: email.clone(),
+        receiver: email,
+        message: format!("RevokeToken {id}\r\n"),
+    });
+
+    Ok(Json("Session revoked"))
+}
@@ -0,0 +1,286 @@
+use crate::config::Config;
+use crate::tokens;
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use argon2::{Argon2, PasswordHash, PasswordVerifier};
+use axum::Json;
+use axum::extract::State;
+use axum::http::header::AUTHORIZATION;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum_serde::macros::Deserialize;
+use serde_json::json;
+use sqlx::{MySql, Pool};
+use std::sync::Arc;
+use totp_rs::{Builder, Secret, Totp};
+
+const ISSUER: &str = "R2M";
+const RECOVERY_CODE_COUNT: usize = 10;
+
+#[derive(Deserialize)]
+pub struct Enroll {
+    password: String,
+}
+
+#[derive(Deserialize)]
+pub struct Enable {
+    code: String,
+}
+
+#[derive(Deserialize)]
+pub struct Disable {
+    password: String,
+    code: String,
+}
+
+/// Starts enrollment with a new secret, which only takes effect once a code from it is confirmed
+pub async fn enroll(
+    headers: HeaderMap,
+    State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
+    Json(payload): Json<Enroll>,
+) -> impl IntoResponse {
+    let token = headers
+        .get(AUTHORIZATION)
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?
+        .to_str()
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?
+        .replace("Bearer ", "");
+
+    let token = tokens::hash(&config.tokens.secret, &token);
+
+    let Ok(user) = sqlx::query!(
+        "SELECT users.id, email, password, totp_enabled as `totp_enabled: bool` FROM users
+        INNER JOIN tokens ON tokens.user_id = users.id
+        WHERE token_hash = ? LIMIT 1",
+        token
+    )
+    .fetch_one(&pool)
+    .await
+    else {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    };
+
+    if user.totp_enabled {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let parsed_hash =
+        PasswordHash::new(&user.password).or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+
+    Argon2::default()
+        .verify_password(payload.password.as_bytes(), &parsed_hash)
+        .or(Err(StatusCode::UNAUTHORIZED))?;
+
+    let secret = Secret::generate().to_base32();
+    let uri = totp(&secret, &user.email)
+        .and_then(|totp| totp.to_url().ok())
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    sqlx::query!(
+        "UPDATE users SET totp_secret = ?, totp_last_step = NULL WHERE id = ?",
+        secret,
+        user.id
+    )
+    .execute(&pool)
+    .await
+    .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+
+    Ok(Json(json!({"secret": secret, "uri": uri})))
+}
+
+/// Turns 2FA on after a code from the enrolled secret is confirmed and returns new recovery codes
+pub async fn enable(
+    headers: HeaderMap,
+    State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
+    Json(payload): Json<Enable>,
+) -> impl IntoResponse {
+    let token = headers
+        .get(AUTHORIZATION)
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?
+        .to_str()
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?
+        .replace("Bearer ", "");
+
+    let token = tokens::hash(&config.tokens.secret, &token);
+
+    let Ok(user) = sqlx::query!(
+        "SELECT users.id, email, totp_secret, totp_enabled as `totp_enabled: bool` FROM users
+        INNER JOIN tokens ON tokens.user_id = users.id
+        WHERE token_hash = ? LIMIT 1",
+        token
+    )
+    .fetch_one(&pool)
+    .await
+    else {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    };
+
+    if user.totp_enabled {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let Some(secret) = user.totp_secret else {
+        return Err(StatusCode::BAD_REQUEST);
+    };
+
+    if !verify_totp(&pool, user.id, &user.email, &secret, &payload.code).await {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let recovery_codes = generate_recovery_codes(&pool, &config, user.id)
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+
+    sqlx::query!("UPDATE users SET totp_enabled = TRUE WHERE id = ?", user.id)
+        .execute(&pool)
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+
+    Ok(Json(json!({"recovery_codes": recovery_codes})))
+}
+
+pub async fn disable(
+    headers: HeaderMap,
+    State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
+    Json(payload): Json<Disable>,
+) -> impl IntoResponse {
+    let token = headers
+        .get(AUTHORIZATION)
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?
+        .to_str()
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?
+        .replace("Bearer ", "");
+
+    let token = tokens::hash(&config.tokens.secret, &token);
+
+    let Ok(user) = sqlx::query!(
+        "SELECT users.id, email, password, totp_secret, totp_enabled as `totp_enabled: bool` FROM users
+        INNER JOIN tokens ON tokens.user_id = users.id
+        WHERE token_hash = ? LIMIT 1",
+        token
+    )
+    .fetch_one(&pool)
+    .await
+    else {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    };
+
+    let (true, Some(secret)) = (user.totp_enabled, user.totp_secret) else {
+        return Err(StatusCode::BAD_REQUEST);
+    };
+
+    let parsed_hash =
+        PasswordHash::new(&user.password).or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+
+    Argon2::default()
+        .verify_password(payload.password.as_bytes(), &parsed_hash)
+        .or(Err(StatusCode::UNAUTHORIZED))?;
+
+    if !verify(&pool, &config, user.id, &user.email, &secret, &payload.code).await {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = ?", user.id)
+        .execute(&pool)
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+
+    sqlx::query!(
+        "UPDATE users SET totp_secret = NULL, totp_enabled = FALSE, totp_last_step = NULL WHERE id = ?",
+        user.id
+    )
+    .execute(&pool)
+    .await
+    .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+
+    Ok(Json("Two-factor authentication disabled"))
+}
+
+/// Checks a code from the authenticator app or, failing that, uses up a matching recovery code
+pub async fn verify(
+    pool: &Pool<MySql>,
+    config: &Config,
+    user_id: i32,
+    email: &str,
+    secret: &str,
+    code: &str,
+) -> bool {
+    if verify_totp(pool, user_id, email, secret, code).await {
+        return true;
+    }
+
+    let code = code.replace('-', "").to_lowercase();
+    sqlx::query!(
+        "DELETE FROM recovery_codes WHERE user_id = ? AND code_hash = ? LIMIT 1",
+        user_id,
+        tokens::hash(&config.tokens.secret, &code)
+    )
+    .execute(pool)
+    .await
+    .is_ok_and(|result| result.rows_affected() == 1)
+}
+
+async fn verify_totp(
+    pool: &Pool<MySql>,
+    user_id: i32,
+    email: &str,
+    secret: &str,
+    code: &str,
+) -> bool {
+    let Some(step) = totp(secret, email).and_then(|totp| totp.check_current(code.trim())) else {
+        return false;
+    };
+
+    // Each code is only accepted once
+    sqlx::query!(
+        "UPDATE users SET totp_last_step = ? WHERE id = ? AND (totp_last_step IS NULL OR totp_last_step < ?)",
+        step as i64,
+        user_id,
+        step as i64
+    )
+    .execute(pool)
+    .await
+    .is_ok_and(|result| result.rows_affected() == 1)
+}
+
+fn totp(secret: &str, email: &str) -> Option<Totp> {
+    Builder::new()
+        .with_secret(Secret::try_from_base32(secret).ok()?)
+        .with_account_name(email)
+        .with_issuer(Some(ISSUER))
+        .build()
+        .ok()
+}
+
+async fn generate_recovery_codes(
+    pool: &Pool<MySql>,
+    config: &Config,
+    user_id: i32,
+) -> Result<Vec<String>, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = ?", user_id)
+        .execute(&mut *transaction)
+        .await?;
+
+    let mut recovery_codes = Vec::with_capacity(RECOVERY_CODE_COUNT);
+    for _ in 0..RECOVERY_CODE_COUNT {
+        let mut bytes = [0u8; 5];
+        OsRng.fill_bytes(&mut bytes);
+
+        let code: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+        sqlx::query!(
+            "INSERT INTO recovery_codes (code_hash, user_id) VALUES (?, ?)",
+            tokens::hash(&config.tokens.secret, &code),
+            user_id
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        recovery_codes.push(format!("{}-{}", &code[..5], &code[5..]));
+    }
+
+    transaction.commit().await?;
+    Ok(recovery_codes)
+}
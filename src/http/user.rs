@@ -1,18 +1,29 @@
+use crate::config::Config;
+use crate::tokens;
 use axum::Json;
 use axum::extract::State;
 use axum::http::header::AUTHORIZATION;
 use axum::http::{HeaderMap, StatusCode};
 use axum::response::IntoResponse;
 use axum_serde::macros::Serialize;
+use chrono::NaiveDateTime;
 use sqlx::{MySql, Pool};
+use std::sync::Arc;
 
 #[derive(Serialize)]
 pub struct UserResponse {
     email: String,
     display_name: String,
+    verified: bool,
+    two_factor_enabled: bool,
+    deletion_scheduled_at: Option<NaiveDateTime>,
 }
 
-pub async fn user(headers: HeaderMap, State(pool): State<Pool<MySql>>) -> impl IntoResponse {
+pub async fn user(
+    headers: HeaderMap,
+    State(pool): State<Pool<MySql>>,
+    State(config): State<Arc<Config>>,
+) -> impl IntoResponse {
     let token = headers
         .get(AUTHORIZATION)
         .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?
@@ -20,9 +31,12 @@ pub async fn user(headers: HeaderMap, State(pool): State<Pool<MySql>>) -> impl I
         .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?
         .replace("Bearer ", "");
 
+    let token = tokens::hash(&config.tokens.secret, &token);
+
     let Ok(user) = sqlx::query!(
-        "SELECT email, display_name FROM users INNER JOIN tokens ON tokens.user_id = users.id
-        WHERE token = ? LIMIT 1",
+        "SELECT email, display_name, verified as `verified: bool`,
+        totp_enabled as `totp_enabled: bool`, deletion_scheduled_at FROM users INNER JOIN tokens ON tokens.user_id = users.id
+        WHERE token_hash = ? LIMIT 1",
         token
     )
     .fetch_one(&pool)
@@ -38,5 +52,8 @@ pub async fn user(headers: HeaderMap, State(pool): State<Pool<MySql>>) -> impl I
     Ok(Json(UserResponse {
         email: user.email,
         display_name,
+        verified: user.verified,
+        two_factor_enabled: user.totp_enabled,
+        deletion_scheduled_at: user.deletion_scheduled_at,
     }))
 }
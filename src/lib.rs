@@ -0,0 +1,27 @@
+//! Only built for the benchmarks, which need the real command handlers
+#![cfg(feature = "bench")]
+#![allow(dead_code, async_fn_in_trait)]
+
+mod accounts;
+mod cluster;
+mod config;
+mod email_invitations;
+mod errors;
+mod http;
+mod limits;
+mod mail;
+mod message;
+mod mobile_pager;
+mod models;
+mod notification_server;
+mod proxy_protocol;
+mod receive_split;
+mod sms;
+mod switchboard;
+mod tokens;
+
+use message::Message;
+
+pub use models::transient::authenticated_user::AuthenticatedUser;
+pub use notification_server::commands::syn::Syn;
+pub use notification_server::commands::traits::user_command::UserCommand;
@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+
+/// Counts open connections per IP address, shared by every listener of a process
+#[derive(Debug, Clone)]
+pub struct ConnectionLimiter {
+    max_per_ip: usize,
+    connections: Arc<Mutex<HashMap<IpAddr, usize>>>,
+}
+
+/// Releases its connection slot when dropped
+#[derive(Debug)]
+pub struct ConnectionGuard {
+    ip: IpAddr,
+    connections: Arc<Mutex<HashMap<IpAddr, usize>>>,
+}
+
+impl ConnectionLimiter {
+    /// A `max_per_ip` of 0 disables the limit
+    pub fn new(max_per_ip: usize) -> Self {
+        ConnectionLimiter {
+            max_per_ip,
+            connections: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn acquire(&self, ip: IpAddr) -> Option<ConnectionGuard> {
+        let ip = ip.to_canonical();
+        let mut connections = self.connections.lock().ok()?;
+        let count = connections.entry(ip).or_insert(0);
+
+        if self.max_per_ip != 0 && *count >= self.max_per_ip {
+            return None;
+        }
+
+        *count += 1;
+        Some(ConnectionGuard {
+            ip,
+            connections: self.connections.clone(),
+        })
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        let Ok(mut connections) = self.connections.lock() else {
+            return;
+        };
+
+        if let Some(count) = connections.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                connections.remove(&self.ip);
+            }
+        }
+    }
+}
@@ -0,0 +1,4 @@
+pub mod connection_limiter;
+pub mod session_limiter;
+pub mod token_bucket;
+pub mod user_limiter;
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Counts the switchboard sessions each user is in, shared by every listener of a process
+#[derive(Debug, Clone)]
+pub struct SessionLimiter {
+    max_per_user: usize,
+    sessions: Arc<Mutex<HashMap<Arc<String>, usize>>>,
+}
+
+/// Releases its session slot when dropped
+#[derive(Debug)]
+pub struct SessionGuard {
+    email: Arc<String>,
+    sessions: Arc<Mutex<HashMap<Arc<String>, usize>>>,
+}
+
+impl SessionLimiter {
+    /// A `max_per_user` of 0 disables the limit
+    pub fn new(max_per_user: usize) -> Self {
+        SessionLimiter {
+            max_per_user,
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn acquire(&self, email: Arc<String>) -> Option<SessionGuard> {
+        let mut sessions = self.sessions.lock().ok()?;
+        let count = sessions.entry(email.clone()).or_insert(0);
+
+        if self.max_per_user != 0 && *count >= self.max_per_user {
+            return None;
+        }
+
+        *count += 1;
+        Some(SessionGuard {
+            email,
+            sessions: self.sessions.clone(),
+        })
+    }
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        let Ok(mut sessions) = self.sessions.lock() else {
+            return;
+        };
+
+        if let Some(count) = sessions.get_mut(&self.email) {
+            *count -= 1;
+            if *count == 0 {
+                sessions.remove(&self.email);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_sessions_past_the_limit() {
+        let limiter = SessionLimiter::new(2);
+        let email = Arc::new(String::from("alice@example.com"));
+
+        let first = limiter.acquire(email.clone());
+        let second = limiter.acquire(email.clone());
+        assert!(first.is_some() && second.is_some());
+        assert!(limiter.acquire(email.clone()).is_none());
+        assert!(
+            limiter
+                .acquire(Arc::new(String::from("bob@example.com")))
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn dropping_a_guard_frees_its_slot() {
+        let limiter = SessionLimiter::new(1);
+        let email = Arc::new(String::from("alice@example.com"));
+
+        let guard = limiter.acquire(email.clone());
+        assert!(limiter.acquire(email.clone()).is_none());
+
+        drop(guard);
+        assert!(limiter.acquire(email.clone()).is_some());
+        assert!(limiter.sessions.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn zero_disables_the_limit() {
+        let limiter = SessionLimiter::new(0);
+        let email = Arc::new(String::from("alice@example.com"));
+
+        let guards: Vec<_> = (0..100).map(|_| limiter.acquire(email.clone())).collect();
+        assert!(guards.iter().all(Option::is_some));
+    }
+}
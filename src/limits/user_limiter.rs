@@ -0,0 +1,44 @@
+use super::token_bucket::TokenBucket;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Limits an action per user across all of their connections, shared by every listener of a process
+#[derive(Debug, Clone)]
+pub struct UserLimiter {
+    per_window: u32,
+    window: Duration,
+    buckets: Arc<Mutex<HashMap<Arc<String>, TokenBucket>>>,
+}
+
+impl UserLimiter {
+    /// A `per_window` of 0 disables the limit
+    pub fn new(per_window: u32, window: Duration) -> Self {
+        UserLimiter {
+            per_window,
+            window,
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn try_take(&self, email: &Arc<String>) -> bool {
+        if self.per_window == 0 {
+            return true;
+        }
+
+        let Ok(mut buckets) = self.buckets.lock() else {
+            return false;
+        };
+
+        buckets.retain(|_, bucket| !bucket.is_full());
+        buckets
+            .entry(email.clone())
+            .or_insert_with(|| {
+                TokenBucket::new(
+                    self.per_window,
+                    self.per_window as f64 / self.window.as_secs_f64(),
+                )
+            })
+            .try_take()
+    }
+}
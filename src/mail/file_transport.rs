@@ -0,0 +1,43 @@
+use super::{mail::Mail, mail_transport::MailTransport};
+use crate::errors::mail_error::MailError;
+use chrono::Utc;
+use log::info;
+use std::path::PathBuf;
+
+/// Writes mails to a directory, or to the log when none is set, for running without a mail server
+#[derive(Debug, Clone)]
+pub struct FileTransport {
+    from: String,
+    directory: Option<PathBuf>,
+}
+
+impl FileTransport {
+    pub fn new(from: String, directory: Option<PathBuf>) -> Self {
+        FileTransport { from, directory }
+    }
+}
+
+impl MailTransport for FileTransport {
+    async fn send(&self, mail: &Mail) -> Result<(), MailError> {
+        let contents = format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\n\r\n{}\r\n",
+            self.from, mail.to, mail.subject, mail.body
+        );
+
+        let Some(directory) = &self.directory else {
+            info!("Mail:\r\n{contents}");
+            return Ok(());
+        };
+
+        tokio::fs::create_dir_all(directory).await?;
+        let path = directory.join(format!(
+            "{}-{}.eml",
+            Utc::now().format("%Y%m%d%H%M%S%f"),
+            mail.to.replace(['/', '\\'], "_")
+        ));
+
+        tokio::fs::write(&path, contents).await?;
+        info!("Wrote mail to {}", path.display());
+        Ok(())
+    }
+}
@@ -0,0 +1,6 @@
+use super::mail::Mail;
+use crate::errors::mail_error::MailError;
+
+pub trait MailTransport {
+    fn send(&self, mail: &Mail) -> impl Future<Output = Result<(), MailError>> + Send;
+}
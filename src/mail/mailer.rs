@@ -0,0 +1,36 @@
+use super::{
+    file_transport::FileTransport, mail::Mail, mail_transport::MailTransport,
+    smtp_transport::SmtpTransport,
+};
+use crate::config::{MailConfig, MailTransportKind};
+use crate::errors::mail_error::MailError;
+
+/// The mail transport selected in the configuration
+#[derive(Clone)]
+pub enum Mailer {
+    Smtp(SmtpTransport),
+    File(FileTransport),
+}
+
+impl Mailer {
+    pub fn new(config: &MailConfig) -> Result<Self, MailError> {
+        Ok(match config.transport {
+            MailTransportKind::Smtp => {
+                Mailer::Smtp(SmtpTransport::new(&config.from, &config.smtp)?)
+            }
+            MailTransportKind::File => Mailer::File(FileTransport::new(
+                config.from.clone(),
+                config.directory.clone(),
+            )),
+        })
+    }
+}
+
+impl MailTransport for Mailer {
+    async fn send(&self, mail: &Mail) -> Result<(), MailError> {
+        match self {
+            Mailer::Smtp(transport) => transport.send(mail).await,
+            Mailer::File(transport) => transport.send(mail).await,
+        }
+    }
+}
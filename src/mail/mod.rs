@@ -0,0 +1,6 @@
+pub mod file_transport;
+#[allow(clippy::module_inception)]
+pub mod mail;
+pub mod mail_transport;
+pub mod mailer;
+pub mod smtp_transport;
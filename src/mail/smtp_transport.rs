@@ -0,0 +1,51 @@
+use super::{mail::Mail, mail_transport::MailTransport};
+use crate::config::SmtpConfig;
+use crate::errors::mail_error::MailError;
+use lettre::message::{Mailbox, header::ContentType};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+#[derive(Clone)]
+pub struct SmtpTransport {
+    from: Mailbox,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpTransport {
+    pub fn new(from: &str, config: &SmtpConfig) -> Result<Self, MailError> {
+        let builder = if config.starttls {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)?
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host)
+        };
+
+        let builder = builder.port(config.port);
+        let builder = if config.username.is_empty() {
+            builder
+        } else {
+            builder.credentials(Credentials::new(
+                config.username.clone(),
+                config.password.clone(),
+            ))
+        };
+
+        Ok(SmtpTransport {
+            from: from.parse()?,
+            transport: builder.build(),
+        })
+    }
+}
+
+impl MailTransport for SmtpTransport {
+    async fn send(&self, mail: &Mail) -> Result<(), MailError> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(mail.to.parse()?)
+            .subject(&mail.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(mail.body.clone())?;
+
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
@@ -1,55 +1,124 @@
+use cluster::registered_node::RegisteredNode;
+use config::{ClusterRole, Config};
 use dotenvy::dotenv;
 use env_logger::Env;
-use log::{error, info};
+use limits::connection_limiter::ConnectionLimiter;
+use limits::session_limiter::SessionLimiter;
+use limits::user_limiter::UserLimiter;
+use log::{error, info, warn};
+use mail::mailer::Mailer;
 use message::Message;
+use mobile_pager::MobilePager;
 use notification_server::notification_server::NotificationServer;
+use sms::pager::Pager;
 use sqlx::MySqlPool;
 use std::sync::Arc;
-use std::{collections::HashMap, env};
-use switchboard::{session::Session, switchboard::Switchboard};
-use tokio::{net::TcpListener, sync::broadcast};
+use std::time::Duration;
+use std::{collections::HashMap, env, io, net::SocketAddr};
+use switchboard::session::{self, Session};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::broadcast,
+};
 
+mod accounts;
+mod cluster;
+mod config;
+mod email_invitations;
 mod errors;
 mod http;
+mod limits;
+mod mail;
 mod message;
+mod mobile_pager;
 pub mod models;
 mod notification_server;
+mod proxy_protocol;
 mod receive_split;
+mod sms;
 mod switchboard;
+mod tokens;
 
 #[tokio::main]
 async fn main() {
     dotenv().ok();
     env_logger::Builder::from_env(Env::default().default_filter_or("trace")).init();
+    let config = Arc::new(Config::load().expect("Could not load configuration"));
+
+    if config.cluster.role == ClusterRole::Switchboard {
+        cluster::node::run(config).await;
+        return;
+    }
+
     let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
 
     let pool = MySqlPool::connect(&database_url)
         .await
         .expect("Could not build connection pool");
 
-    let notification_server_listener = TcpListener::bind("0.0.0.0:1863")
+    let pager = Pager::new(&config.sms).expect("Could not set up SMS gateway");
+    let mobile_pager = MobilePager::new(pool.clone(), &config, pager);
+    let mailer = Mailer::new(&config.mail).expect("Could not set up mail transport");
+
+    let notification_server_listener = TcpListener::bind(&config.notification_server.bind_address)
         .await
         .expect("Could not bind Notification Server");
 
-    info!("Notification Server listening on port 1863");
+    info!(
+        "Notification Server listening on {}",
+        config.notification_server.bind_address
+    );
 
-    let switchboard_listener = TcpListener::bind("0.0.0.0:1864")
-        .await
-        .expect("Could not bind Switchboard");
+    let switchboard_listener = if config.cluster.local_switchboard {
+        let listener = TcpListener::bind(&config.switchboard.bind_address)
+            .await
+            .expect("Could not bind Switchboard");
 
-    info!("Switchboard listening on port 1864");
+        info!(
+            "Switchboard listening on {}",
+            config.switchboard.bind_address
+        );
 
-    let (tx, mut rx) = broadcast::channel::<Message>(64);
-    tokio::spawn(http::listen(pool.clone(), tx.clone()));
+        Some(listener)
+    } else {
+        None
+    };
 
+    let (tx, mut rx) = broadcast::channel::<Message>(config.channels.broadcast_capacity);
+    tokio::spawn(http::listen(
+        pool.clone(),
+        tx.clone(),
+        config.clone(),
+        mailer.clone(),
+    ));
+    tokio::spawn(cluster::control_server::listen(tx.clone(), config.clone()));
+    tokio::spawn(tokens::purge_expired(pool.clone()));
+    tokio::spawn(accounts::purge_scheduled(pool.clone(), tx.clone()));
+    if config.features.require_email_verification {
+        tokio::spawn(accounts::purge_unverified(
+            pool.clone(),
+            tx.clone(),
+            config.tokens.email_confirmation_lifetime_hours,
+        ));
+    }
+
+    let connection_limiter = ConnectionLimiter::new(config.limits.connections_per_ip);
+    let search_limiter =
+        UserLimiter::new(config.limits.searches_per_minute, Duration::from_secs(60));
+    let session_limiter = SessionLimiter::new(config.limits.sessions_per_user);
+    let ticket_lifetime = Duration::from_secs(config.switchboard.ticket_lifetime_seconds);
+    let mut session_sweep = tokio::time::interval(session::SWEEP_INTERVAL);
+    let switchboard_address = Arc::new(config.switchboard_address());
     let mut channels: HashMap<Arc<String>, broadcast::Sender<Message>> = HashMap::new();
     let mut sessions: HashMap<Arc<String>, Session> = HashMap::new();
+    let mut tickets: HashMap<Arc<String>, Arc<String>> = HashMap::new();
+    let mut switchboards: HashMap<Arc<String>, RegisteredNode> = HashMap::new();
     let mut user_count: u32 = 0;
 
     loop {
         tokio::select! {
             client = notification_server_listener.accept() => {
-                let (mut socket, _) = match client {
+                let (mut socket, accepted_address) = match client {
                     Ok(client) => client,
                     Err(error) => {
                         error!("Could not get socket from accepted Notification Server connection: {error}");
@@ -59,20 +128,53 @@ async fn main() {
 
                 let pool = pool.clone();
                 let tx = tx.clone();
+                let config = config.clone();
+                let mobile_pager = mobile_pager.clone();
+                let search_limiter = search_limiter.clone();
+                let mailer = mailer.clone();
+                let connection_limiter = connection_limiter.clone();
 
                 tokio::spawn(async move {
-                    let mut connection = NotificationServer::new(pool, tx.clone());
+                    let peer_address = match proxy_protocol::peer_address(
+                        &mut socket,
+                        accepted_address,
+                        config.notification_server.proxy_protocol,
+                    )
+                    .await
+                    {
+                        Ok(peer_address) => peer_address,
+                        Err(error) => {
+                            warn!("Rejected Notification Server connection from {accepted_address}: {error}");
+                            return;
+                        }
+                    };
+
+                    let Some(_connection_guard) = connection_limiter.acquire(peer_address.ip()) else {
+                        warn!("Rejected Notification Server connection from {peer_address}: too many connections");
+                        return;
+                    };
+
+                    info!("Notification Server connection from {peer_address}");
+                    let mut connection = NotificationServer::new(
+                        pool,
+                        tx.clone(),
+                        config,
+                        mobile_pager,
+                        search_limiter,
+                        mailer,
+                        peer_address,
+                    );
                     loop {
                         if let Err(error) = connection.listen(&mut socket).await {
-                            error!("{error}");
+                            error!("{peer_address}: {error}");
                             break;
                         }
                     }
                 });
             }
 
-            client = switchboard_listener.accept() => {
-                let (mut socket, _) = match client {
+            client = accept(switchboard_listener.as_ref()) => {
+                let (socket, accepted_address) = match client {
                     Ok(client) => client,
                     Err(error) => {
                         error!("Could not get socket from accepted Switchboard connection: {error}");
@@ -80,16 +182,11 @@ async fn main() {
                     }
                 };
 
-                let tx = tx.clone();
-                tokio::spawn(async move {
-                    let mut connection = Switchboard::new(tx.clone());
-                    loop {
-                        if let Err(error) = connection.listen(&mut socket).await {
-                            error!("{error}");
-                            break;
-                        }
-                    }
-                });
+                tokio::spawn(switchboard::switchboard::serve(socket, accepted_address, tx.clone(), config.clone(), connection_limiter.clone(), session_limiter.clone()));
+            }
+
+            _ = session_sweep.tick() => {
+                session::sweep(&mut sessions, &mut tickets, ticket_lifetime);
             }
 
             message = rx.recv() => {
@@ -138,7 +235,7 @@ async fn main() {
                     }
 
                     Message::GetSession(key) => {
-                        let session = sessions.get(&key);
+                        let session = session::find(&sessions, &tickets, &key);
                         if let Err(error) = tx.send(Message::Session { key: key.clone(), value: session.cloned() }) {
                             error!("Could not send session to {key}: {error}");
                         }
@@ -149,7 +246,102 @@ async fn main() {
                     }
 
                     Message::RemoveSession(key) => {
-                        sessions.remove(&key);
+                        session::remove(&mut sessions, &mut tickets, &key);
+                    }
+
+                    Message::EndSessions(email) => {
+                        session::end_sessions(&sessions, &email);
+                        for node in switchboards.values() {
+                            if let Err(error) = node.node_tx.send(Message::EndSessions(email.clone())) {
+                                error!("Could not end sessions on switchboard node {}: {error}", node.address);
+                            }
+                        }
+                    }
+
+                    Message::SetTicket { key, value } => {
+                        tickets.insert(key, value);
+                    }
+
+                    Message::RemoveTicket(key) => {
+                        tickets.remove(&key);
+                    }
+
+                    Message::SetDisplayName { email, display_name } => {
+                        for session in sessions.values() {
+                            if let Ok(mut principals) = session.principals.lock()
+                                && let Some(principal) = principals.get_mut(&email) {
+                                principal.display_name = display_name.clone();
+                            }
+                        }
+
+                        for node in switchboards.values() {
+                            if let Err(error) = node.node_tx.send(Message::SetDisplayName { email: email.clone(), display_name: display_name.clone() }) {
+                                error!("Could not send display name to switchboard node {}: {error}", node.address);
+                            }
+                        }
+                    }
+
+                    Message::SetBlocked { email, contact, blocked } => {
+                        for session in sessions.values() {
+                            if let Ok(mut principals) = session.principals.lock()
+                                && let Some(principal) = principals.get_mut(&email) {
+                                if blocked {
+                                    principal.blocked.insert(contact.clone());
+                                } else {
+                                    principal.blocked.remove(&contact);
+                                }
+                            }
+                        }
+
+                        for node in switchboards.values() {
+                            if let Err(error) = node.node_tx.send(Message::SetBlocked { email: email.clone(), contact: contact.clone(), blocked }) {
+                                error!("Could not send block list change to switchboard node {}: {error}", node.address);
+                            }
+                        }
+                    }
+
+                    Message::AssignSession { session_id, cki_string, email } => {
+                        let local_load = config.cluster.local_switchboard.then_some(sessions.len());
+                        let node = switchboards
+                            .values_mut()
+                            .filter(|node| local_load.is_none_or(|load| node.load < load))
+                            .min_by_key(|node| node.load);
+
+                        if let Some(node) = node {
+                            node.load += 1;
+                            if let Err(error) = node.node_tx.send(Message::CreateSession { session_id, cki_string: cki_string.clone(), email }) {
+                                error!("Could not send session to switchboard node {}: {error}", node.address);
+                                if let Err(error) = tx.send(Message::SessionAssigned { key: cki_string.clone(), address: None }) {
+                                    error!("Could not assign session {cki_string}: {error}");
+                                }
+                            }
+                        } else {
+                            let address = if local_load.is_some() {
+                                let session = Session::new(session_id, cki_string.clone(), email, config.channels.session_capacity, ticket_lifetime);
+                                sessions.insert(cki_string.clone(), session);
+                                Some(switchboard_address.clone())
+                            } else {
+                                None
+                            };
+
+                            if let Err(error) = tx.send(Message::SessionAssigned { key: cki_string.clone(), address }) {
+                                error!("Could not assign session {cki_string}: {error}");
+                            }
+                        }
+                    }
+
+                    Message::AddSwitchboard { key, address, value } => {
+                        switchboards.insert(key, RegisteredNode { address, load: 0, node_tx: value });
+                    }
+
+                    Message::RemoveSwitchboard(key) => {
+                        switchboards.remove(&key);
+                    }
+
+                    Message::SwitchboardLoad { key, value } => {
+                        if let Some(node) = switchboards.get_mut(&key) {
+                            node.load = value;
+                        }
                     }
 
                     Message::GetUsers => {
@@ -166,6 +358,27 @@ async fn main() {
                         user_count -= 1;
                     }
 
+                    // Switchboards ask about offline contacts they were called into, to fall back to their mobile device
+                    Message::GetMobile { sender, receiver } => {
+                        let mobile_pager = mobile_pager.clone();
+                        let tx = tx.clone();
+                        tokio::spawn(async move {
+                            let reachable = mobile_pager.phone_number(&sender, &receiver).await.is_ok();
+                            if let Err(error) = tx.send(Message::Mobile { sender: receiver, receiver: sender.clone(), reachable }) {
+                                error!("Could not send mobile status to {sender}: {error}");
+                            }
+                        });
+                    }
+
+                    Message::Page { sender, receiver, message } => {
+                        let mobile_pager = mobile_pager.clone();
+                        tokio::spawn(async move {
+                            if let Err(error) = mobile_pager.page(&sender, &receiver, message).await {
+                                warn!("Did not page {receiver} for {sender}: {error}");
+                            }
+                        });
+                    }
+
                     Message::SendUserDetails { receiver, sender, authenticated_user, protocol_version } => {
                         if let Err(error) = tx.send(Message::UserDetails {
                             sender,
@@ -183,3 +396,10 @@ async fn main() {
         }
     }
 }
+
+async fn accept(listener: Option<&TcpListener>) -> io::Result<(TcpStream, SocketAddr)> {
+    match listener {
+        Some(listener) => listener.accept().await,
+        None => std::future::pending().await,
+    }
+}
@@ -36,11 +36,50 @@ pub enum Message {
 
     RemoveSession(Arc<String>),
 
+    /// Closes the switchboard connections of a deleted account, first sent to the session maps and then to each session
+    EndSessions(Arc<String>),
+
+    /// A ticket issued by CAL, keyed to the CKI of its session
+    SetTicket {
+        key: Arc<String>,
+        value: Arc<String>,
+    },
+
+    RemoveTicket(Arc<String>),
+
     Session {
         key: Arc<String>,
         value: Option<Session>,
     },
 
+    SetDisplayName {
+        email: Arc<String>,
+        display_name: Arc<String>,
+    },
+
+    SetBlocked {
+        email: Arc<String>,
+        contact: Arc<String>,
+        blocked: bool,
+    },
+
+    GetMobile {
+        sender: Arc<String>,
+        receiver: Arc<String>,
+    },
+
+    Mobile {
+        sender: Arc<String>,
+        receiver: Arc<String>,
+        reachable: bool,
+    },
+
+    Page {
+        sender: Arc<String>,
+        receiver: Arc<String>,
+        message: String,
+    },
+
     ToPrincipals {
         sender: Arc<String>,
         message: Vec<u8>,
@@ -60,6 +99,36 @@ pub enum Message {
         protocol_version: Option<u32>,
     },
 
+    AssignSession {
+        session_id: Arc<String>,
+        cki_string: Arc<String>,
+        email: Arc<String>,
+    },
+
+    CreateSession {
+        session_id: Arc<String>,
+        cki_string: Arc<String>,
+        email: Arc<String>,
+    },
+
+    SessionAssigned {
+        key: Arc<String>,
+        address: Option<Arc<String>>,
+    },
+
+    AddSwitchboard {
+        key: Arc<String>,
+        address: Arc<String>,
+        value: Sender<Message>,
+    },
+
+    RemoveSwitchboard(Arc<String>),
+
+    SwitchboardLoad {
+        key: Arc<String>,
+        value: usize,
+    },
+
     UserCount(u32),
     GetUsers,
     AddUser,
@@ -0,0 +1,88 @@
+use crate::config::Config;
+use crate::errors::page_error::PageError;
+use crate::limits::user_limiter::UserLimiter;
+use crate::sms::{pager::Pager, sms::Sms, sms_gateway::SmsGateway};
+use log::{error, info};
+use sqlx::{MySql, Pool};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Sends pages to users' mobile devices, from PAG or switchboard sessions they were called into while offline
+#[derive(Clone)]
+pub struct MobilePager {
+    pool: Pool<MySql>,
+    pager: Pager,
+    limiter: UserLimiter,
+}
+
+impl MobilePager {
+    pub fn new(pool: Pool<MySql>, config: &Config, pager: Pager) -> Self {
+        MobilePager {
+            pool,
+            pager,
+            limiter: UserLimiter::new(config.limits.pages_per_hour, Duration::from_secs(60 * 60)),
+        }
+    }
+
+    /// The mobile number of `email`, if they allow contacts to reach their mobile device and have `sender` on their allow list
+    pub async fn phone_number(&self, sender: &str, email: &str) -> Result<String, PageError> {
+        let recipient = sqlx::query!(
+            "SELECT contacts.in_allow_list as `in_allow_list: bool`,
+            contacts.in_block_list as `in_block_list: bool`, mob.value as mob, phm.value as phm
+            FROM users
+            LEFT JOIN users senders ON senders.email = ?
+            LEFT JOIN contacts ON contacts.user_id = users.id AND contacts.contact_id = senders.id
+            LEFT JOIN user_properties mob ON mob.user_id = users.id AND mob.name = 'MOB'
+            LEFT JOIN user_properties phm ON phm.user_id = users.id AND phm.name = 'PHM'
+            WHERE users.email = ? AND users.verified = TRUE LIMIT 1",
+            sender,
+            email
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(PageError::UserNotFound)?;
+
+        // BLP AL alone would let anyone who added the user page them
+        let allowed =
+            recipient.in_block_list != Some(true) && recipient.in_allow_list == Some(true);
+
+        let (Some("Y"), Some(phone_number), true) =
+            (recipient.mob.as_deref(), recipient.phm, allowed)
+        else {
+            return Err(PageError::Unreachable);
+        };
+
+        Ok(urlencoding::decode(&phone_number)
+            .map(|phone_number| phone_number.into_owned())
+            .unwrap_or(phone_number))
+    }
+
+    /// Checks that `email` can be paged and counts the page against the sender's limit, then sends it in the background
+    pub async fn page(
+        &self,
+        sender: &Arc<String>,
+        email: &str,
+        text: String,
+    ) -> Result<(), PageError> {
+        let phone_number = self.phone_number(sender, email).await?;
+        if !self.limiter.try_take(sender) {
+            return Err(PageError::LimitReached);
+        }
+
+        let sms = Sms {
+            from: sender.to_string(),
+            to: phone_number,
+            body: text,
+        };
+
+        let pager = self.pager.clone();
+        tokio::spawn(async move {
+            match pager.send(&sms).await {
+                Ok(()) => info!("Paged {} from {}", sms.to, sms.from),
+                Err(error) => error!("Could not page {} from {}: {error}", sms.to, sms.from),
+            }
+        });
+
+        Ok(())
+    }
+}
@@ -1,8 +1,9 @@
 use super::transient_contact::TransientContact;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthenticatedUser {
     pub email: Arc<String>,
     pub display_name: Arc<String>,
@@ -12,6 +13,7 @@ pub struct AuthenticatedUser {
     pub personal_message: Option<Arc<String>>,
     pub blp: Arc<String>,
     pub contacts: HashMap<Arc<String>, TransientContact>,
+    pub token_id: Option<i32>,
 }
 
 impl AuthenticatedUser {
@@ -25,6 +27,15 @@ impl AuthenticatedUser {
             personal_message: None,
             blp: Arc::new("AL".to_string()),
             contacts: HashMap::new(),
+            token_id: None,
         }
     }
+
+    pub fn block_list(&self) -> HashSet<Arc<String>> {
+        self.contacts
+            .values()
+            .filter(|contact| contact.in_block_list)
+            .map(|contact| contact.email.clone())
+            .collect()
+    }
 }
@@ -1,3 +1,4 @@
 pub mod authenticated_user;
 pub mod principal;
+pub mod ticket;
 pub mod transient_contact;
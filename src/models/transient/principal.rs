@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::sync::Arc;
 
 #[derive(Debug)]
@@ -5,4 +6,7 @@ pub struct Principal {
     pub email: Arc<String>,
     pub display_name: Arc<String>,
     pub client_id: Option<usize>,
+    pub blocked: HashSet<Arc<String>>,
+    /// Offline contacts called into the session receive its messages as pages
+    pub mobile: bool,
 }
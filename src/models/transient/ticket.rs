@@ -0,0 +1,8 @@
+use std::sync::Arc;
+use std::time::Instant;
+
+#[derive(Debug)]
+pub struct Ticket {
+    pub email: Arc<String>,
+    pub expires_at: Instant,
+}
@@ -1,6 +1,7 @@
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransientContact {
     pub email: Arc<String>,
     pub display_name: Arc<String>,
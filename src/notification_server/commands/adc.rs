@@ -1,10 +1,12 @@
 use super::traits::user_command::UserCommand;
+use crate::email_invitations::EmailInviter;
 use crate::errors::command_error::CommandError;
 use crate::message::Message;
 use crate::models::contact::Contact;
 use crate::models::transient::authenticated_user::AuthenticatedUser;
 use crate::models::transient::transient_contact::TransientContact;
 use crate::notification_server::commands::fln;
+use crate::notification_server::{list_version, pending_additions};
 use sqlx::{MySql, Pool};
 use std::sync::Arc;
 use tokio::sync::broadcast;
@@ -12,11 +14,21 @@ use tokio::sync::broadcast;
 pub struct Adc {
     pool: Pool<MySql>,
     broadcast_tx: broadcast::Sender<Message>,
+    email_inviter: Option<EmailInviter>,
 }
 
 impl Adc {
-    pub fn new(pool: Pool<MySql>, broadcast_tx: broadcast::Sender<Message>) -> Self {
-        Adc { pool, broadcast_tx }
+    /// Without an `email_inviter`, adding addresses that aren't registered sends no invitation
+    pub fn new(
+        pool: Pool<MySql>,
+        broadcast_tx: broadcast::Sender<Message>,
+        email_inviter: Option<EmailInviter>,
+    ) -> Self {
+        Adc {
+            pool,
+            broadcast_tx,
+            email_inviter,
+        }
     }
 }
 
@@ -28,7 +40,6 @@ impl UserCommand for Adc {
         user: &mut AuthenticatedUser,
         version_number: &mut u32,
     ) -> Result<Vec<String>, CommandError> {
-        let _ = version_number;
         let args: Vec<&str> = command.trim().split(' ').collect();
         let tr_id = *args.get(1).ok_or(CommandError::NoTrId)?;
 
@@ -67,14 +78,29 @@ impl UserCommand for Adc {
                 return Err(CommandError::Reply(format!("201 {tr_id}\r\n")));
             }
 
-            let contact_user = sqlx::query!(
-                "SELECT id, guid FROM users WHERE email = ? LIMIT 1",
+            let Ok(contact_user) = sqlx::query!(
+                "SELECT id, guid FROM users WHERE email = ? AND verified = TRUE LIMIT 1",
                 *contact_email
             )
             .fetch_one(&self.pool)
             .await
-            .or(Err(CommandError::Reply(format!("208 {tr_id}\r\n"))))?;
+            else {
+                // Real MSN offered to invite addresses that weren't registered yet
+                if forward_list && let Some(email_inviter) = &self.email_inviter {
+                    email_inviter.send_in_background(user.email.clone(), contact_email.to_string());
+                }
+
+                return Err(CommandError::Reply(format!("208 {tr_id}\r\n")));
+            };
 
+            // The contact change and the pending addition are written together
+            let mut transaction = self
+                .pool
+                .begin()
+                .await
+                .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+
+            let mut new_contact = None;
             if let Ok(contact) = sqlx::query_as!(
                 Contact,
                 "SELECT contacts.id, user_id, contact_id, contacts.display_name, email, guid,
@@ -99,16 +125,12 @@ impl UserCommand for Adc {
                         "UPDATE contacts SET in_forward_list = TRUE WHERE id = ?",
                         contact.id
                     )
-                    .execute(&self.pool)
+                    .execute(&mut *transaction)
                     .await
                     .is_err()
                     {
                         return Err(CommandError::Reply(format!("603 {tr_id}\r\n")));
                     }
-
-                    if let Some(contact) = user.contacts.get_mut(&contact_email) {
-                        contact.in_forward_list = forward_list;
-                    };
                 } else if allow_list {
                     if contact.in_allow_list {
                         return Err(CommandError::Reply(format!("215 {tr_id}\r\n")));
@@ -118,16 +140,12 @@ impl UserCommand for Adc {
                         "UPDATE contacts SET in_allow_list = TRUE WHERE id = ?",
                         contact.id
                     )
-                    .execute(&self.pool)
+                    .execute(&mut *transaction)
                     .await
                     .is_err()
                     {
                         return Err(CommandError::Reply(format!("603 {tr_id}\r\n")));
                     }
-
-                    if let Some(contact) = user.contacts.get_mut(&contact_email) {
-                        contact.in_allow_list = allow_list;
-                    };
                 } else if block_list {
                     if contact.in_block_list {
                         return Err(CommandError::Reply(format!("215 {tr_id}\r\n")));
@@ -137,16 +155,12 @@ impl UserCommand for Adc {
                         "UPDATE contacts SET in_block_list = TRUE WHERE id = ?",
                         contact.id
                     )
-                    .execute(&self.pool)
+                    .execute(&mut *transaction)
                     .await
                     .is_err()
                     {
                         return Err(CommandError::Reply(format!("603 {tr_id}\r\n")));
                     }
-
-                    if let Some(contact) = user.contacts.get_mut(&contact_email) {
-                        contact.in_block_list = block_list;
-                    };
                 }
             } else {
                 let contact_display_name = if forward_list && let Some(display_name) = args.get(4) {
@@ -155,7 +169,7 @@ impl UserCommand for Adc {
                     contact_email.clone()
                 };
 
-                if sqlx::query!(
+                sqlx::query!(
                     "INSERT INTO contacts (user_id, contact_id, display_name, in_forward_list, in_allow_list, in_block_list)
                     VALUES (?, ?, ?, ?, ?, ?)",
                     database_user.id,
@@ -165,28 +179,58 @@ impl UserCommand for Adc {
                     allow_list,
                     block_list
                 )
-                .execute(&self.pool)
+                .execute(&mut *transaction)
                 .await
-                .is_err()
-                {
-                    return Err(CommandError::Reply(format!("603 {tr_id}\r\n")));
-                }
-
-                user.contacts.insert(
-                    contact_email.clone(),
-                    TransientContact {
-                        email: contact_email.clone(),
-                        display_name: contact_display_name,
-                        presence: None,
-                        msn_object: None,
-                        in_forward_list: forward_list,
-                        in_allow_list: allow_list,
-                        in_block_list: block_list,
-                    },
-                );
+                .map_err(|error| match error {
+                    sqlx::Error::Database(error) if error.is_unique_violation() => {
+                        CommandError::Reply(format!("215 {tr_id}\r\n"))
+                    }
+                    _ => CommandError::Reply(format!("603 {tr_id}\r\n")),
+                })?;
+
+                new_contact = Some(TransientContact {
+                    email: contact_email.clone(),
+                    display_name: contact_display_name,
+                    presence: None,
+                    msn_object: None,
+                    in_forward_list: forward_list,
+                    in_allow_list: allow_list,
+                    in_block_list: block_list,
+                });
             };
 
+            // Adding someone asks them about it, allowing or blocking them answers it
+            if forward_list {
+                pending_additions::add(&mut *transaction, contact_user.id, database_user.id).await
+            } else {
+                pending_additions::resolve(&mut *transaction, database_user.id, contact_user.id)
+                    .await
+            }
+            .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+
+            transaction
+                .commit()
+                .await
+                .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+
+            if let Some(new_contact) = new_contact {
+                user.contacts.insert(contact_email.clone(), new_contact);
+            } else if let Some(contact) = user.contacts.get_mut(&contact_email) {
+                contact.in_forward_list |= forward_list;
+                contact.in_allow_list |= allow_list;
+                contact.in_block_list |= block_list;
+            }
+
+            *version_number = list_version::bump(&self.pool, database_user.id)
+                .await
+                .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+
             return if forward_list {
+                // The contact's reverse list changed as well
+                list_version::bump(&self.pool, contact_user.id)
+                    .await
+                    .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+
                 let contact_guid = contact_user.guid;
                 let contact_display_name = *args
                     .get(4)
@@ -217,6 +261,14 @@ impl UserCommand for Adc {
                     self.broadcast_tx
                         .send(message)
                         .map_err(CommandError::CouldNotSendToBroadcast)?;
+
+                    self.broadcast_tx
+                        .send(Message::SetBlocked {
+                            email: user.email.clone(),
+                            contact: contact_email.clone(),
+                            blocked: true,
+                        })
+                        .map_err(CommandError::CouldNotSendToBroadcast)?;
                 }
 
                 Ok(vec![format!("ADC {tr_id} {list} N={contact_email}\r\n")])
@@ -269,7 +321,16 @@ impl UserCommand for Adc {
             )
             .execute(&self.pool)
             .await
-            .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+            .map_err(|error| match error {
+                sqlx::Error::Database(error) if error.is_unique_violation() => {
+                    CommandError::Reply(format!("215 {tr_id}\r\n"))
+                }
+                _ => CommandError::Reply(format!("603 {tr_id}\r\n")),
+            })?;
+
+            *version_number = list_version::bump(&self.pool, database_user.id)
+                .await
+                .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
 
             return Ok(vec![format!(
                 "ADC {tr_id} {list} C={contact_guid} {group_guid}\r\n"
@@ -1,6 +1,7 @@
 use super::traits::user_command::UserCommand;
 use crate::errors::command_error::CommandError;
 use crate::models::transient::authenticated_user::AuthenticatedUser;
+use crate::notification_server::list_version;
 use sqlx::{MySql, Pool};
 
 pub struct Adg {
@@ -33,10 +34,14 @@ impl UserCommand for Adg {
                 .await
                 .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
 
-        if sqlx::query!("SELECT id FROM groups WHERE name = ?", group_name)
-            .fetch_one(&self.pool)
-            .await
-            .is_ok()
+        if sqlx::query!(
+            "SELECT id FROM groups WHERE name = ? AND user_id = ?",
+            group_name,
+            database_user.id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .is_ok()
         {
             Err(CommandError::Reply(format!("228 {tr_id}\r\n")))
         } else {
@@ -51,6 +56,10 @@ impl UserCommand for Adg {
             .await
             .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
 
+            *version_number = list_version::bump(&self.pool, database_user.id)
+                .await
+                .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+
             Ok(vec![if protocol_version >= 10 {
                 format!("ADG {tr_id} 1 {group_name} {group_guid}\r\n")
             } else {
@@ -59,7 +68,6 @@ impl UserCommand for Adg {
                     .await
                     .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
 
-                *version_number += 1;
                 format!(
                     "ADG {tr_id} {version_number} {group_name} {} 0\r\n",
                     group.id
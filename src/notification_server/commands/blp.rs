@@ -1,6 +1,7 @@
 use super::traits::user_command::UserCommand;
 use crate::errors::command_error::CommandError;
 use crate::models::transient::authenticated_user::AuthenticatedUser;
+use crate::notification_server::list_version;
 use sqlx::{MySql, Pool};
 use std::sync::Arc;
 
@@ -29,10 +30,16 @@ impl UserCommand for Blp {
             .ok_or(CommandError::Reply(format!("201 {tr_id}\r\n")))?;
 
         if setting == "AL" || setting == "BL" {
+            let database_user =
+                sqlx::query!("SELECT id FROM users WHERE email = ? LIMIT 1", *user.email)
+                    .fetch_one(&self.pool)
+                    .await
+                    .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+
             if sqlx::query!(
-                "UPDATE users SET blp = ? WHERE email = ?",
+                "UPDATE users SET blp = ? WHERE id = ?",
                 setting,
-                *user.email
+                database_user.id
             )
             .execute(&self.pool)
             .await
@@ -42,12 +49,14 @@ impl UserCommand for Blp {
             }
 
             user.blp = Arc::new(setting.to_string());
+            *version_number = list_version::bump(&self.pool, database_user.id)
+                .await
+                .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
         }
 
         Ok(vec![if protocol_version >= 10 {
             command.to_string()
         } else {
-            *version_number += 1;
             format!("BLP {tr_id} {version_number} {setting}\r\n")
         }])
     }
@@ -1,7 +1,17 @@
 use super::traits::command::Command;
+use crate::config::Config;
 use crate::errors::command_error::CommandError;
+use std::sync::Arc;
 
-pub struct Cvr;
+pub struct Cvr {
+    config: Arc<Config>,
+}
+
+impl Cvr {
+    pub fn new(config: Arc<Config>) -> Self {
+        Cvr { config }
+    }
+}
 
 impl Command for Cvr {
     async fn handle(
@@ -14,8 +24,14 @@ impl Command for Cvr {
         let args: Vec<&str> = command.trim().split(' ').collect();
         let tr_id = *args.get(1).ok_or(CommandError::NoTrId)?;
 
+        let cvr = &self.config.cvr;
+        let recommended_version = &cvr.recommended_version;
+        let minimum_version = &cvr.minimum_version;
+        let download_url = &cvr.download_url;
+        let info_url = &cvr.info_url;
+
         Ok(vec![format!(
-            "CVR {tr_id} 1.0.0000 1.0.0000 1.0.0000 https://r2m.camposs.net/storage https://r2m.camposs.net\r\n"
+            "CVR {tr_id} {recommended_version} {recommended_version} {minimum_version} {download_url} {info_url}\r\n"
         )])
     }
 }
@@ -0,0 +1,124 @@
+use super::traits::user_command::UserCommand;
+use crate::errors::command_error::CommandError;
+use crate::limits::user_limiter::UserLimiter;
+use crate::models::transient::authenticated_user::AuthenticatedUser;
+use sqlx::{MySql, Pool};
+
+const MAX_RESULTS: i64 = 20;
+
+pub struct Fnd {
+    pool: Pool<MySql>,
+    search_limiter: UserLimiter,
+}
+
+impl Fnd {
+    pub fn new(pool: Pool<MySql>, search_limiter: UserLimiter) -> Self {
+        Fnd {
+            pool,
+            search_limiter,
+        }
+    }
+}
+
+impl UserCommand for Fnd {
+    async fn handle(
+        &self,
+        protocol_version: u32,
+        command: &str,
+        user: &mut AuthenticatedUser,
+        version_number: &mut u32,
+    ) -> Result<Vec<String>, CommandError> {
+        let _ = version_number;
+        let args: Vec<&str> = command.trim().split(' ').collect();
+
+        let tr_id = *args.get(1).ok_or(CommandError::NoTrId)?;
+        if protocol_version >= 10 {
+            return Err(CommandError::Reply(format!("502 {tr_id}\r\n")));
+        }
+
+        // Fields are sent as fname=First lname=Last city=* state=* country=*, with * matching anything
+        let field = |name: &str| {
+            args.iter()
+                .skip(2)
+                .find_map(|arg| arg.strip_prefix(name)?.strip_prefix('='))
+                .and_then(|value| urlencoding::decode(value).ok())
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+                .unwrap_or("*".to_string())
+        };
+
+        let first_name = field("fname");
+        let last_name = field("lname");
+        let city = field("city");
+        let state = field("state");
+        let country = field("country");
+
+        if first_name == "*" || last_name == "*" {
+            return Err(CommandError::Reply(format!("201 {tr_id}\r\n")));
+        }
+
+        if !self.search_limiter.try_take(&user.email) {
+            return Err(CommandError::Reply(format!("800 {tr_id}\r\n")));
+        }
+
+        // Only listed profiles of users who would let the searcher see them online are found
+        let profiles = sqlx::query!(
+            "SELECT profiles.first_name, profiles.last_name, profiles.city, profiles.state, profiles.country
+            FROM profiles
+            INNER JOIN users ON users.id = profiles.user_id
+            LEFT JOIN users searchers ON searchers.email = ?
+            LEFT JOIN contacts ON contacts.user_id = users.id AND contacts.contact_id = searchers.id
+            WHERE profiles.listed = TRUE AND users.verified = TRUE AND users.deletion_scheduled_at IS NULL
+            AND profiles.first_name = ? AND profiles.last_name = ?
+            AND (? = '*' OR profiles.city = ?)
+            AND (? = '*' OR profiles.state = ?)
+            AND (? = '*' OR profiles.country = ?)
+            AND (contacts.in_block_list IS NULL OR contacts.in_block_list = FALSE)
+            AND (users.blp = 'AL' OR contacts.in_allow_list = TRUE)
+            ORDER BY profiles.country, profiles.state, profiles.city LIMIT ?",
+            *user.email,
+            first_name,
+            last_name,
+            city,
+            city,
+            state,
+            state,
+            country,
+            country,
+            MAX_RESULTS
+        )
+        .fetch_all(&self.pool)
+        .await
+        .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+
+        if profiles.is_empty() {
+            return Ok(vec![format!("FND {tr_id} 0 0\r\n")]);
+        }
+
+        let count = profiles.len();
+        Ok(profiles
+            .into_iter()
+            .enumerate()
+            .map(|(index, profile)| {
+                format!(
+                    "FND {tr_id} {} {count} fname={} lname={} city={} state={} country={}\r\n",
+                    index + 1,
+                    encode(&profile.first_name),
+                    encode(&profile.last_name),
+                    encode(&profile.city),
+                    encode(&profile.state),
+                    encode(&profile.country)
+                )
+            })
+            .collect())
+    }
+}
+
+/// Fields left empty are sent as a wildcard, like the ones a search left out
+fn encode(field: &str) -> String {
+    if field.is_empty() {
+        return "*".to_string();
+    }
+
+    urlencoding::encode(field).into_owned()
+}
@@ -1,6 +1,7 @@
 use super::traits::user_command::UserCommand;
 use crate::errors::command_error::CommandError;
 use crate::models::transient::authenticated_user::AuthenticatedUser;
+use crate::notification_server::list_version;
 use sqlx::{MySql, Pool};
 
 pub struct Gtc {
@@ -29,23 +30,33 @@ impl UserCommand for Gtc {
             .get(2)
             .ok_or(CommandError::Reply(format!("201 {tr_id}\r\n")))?;
 
-        if (setting == "A" || setting == "N")
-            && sqlx::query!(
-                "UPDATE users SET gtc = ? WHERE email = ?",
+        if setting == "A" || setting == "N" {
+            let database_user =
+                sqlx::query!("SELECT id FROM users WHERE email = ? LIMIT 1", *user.email)
+                    .fetch_one(&self.pool)
+                    .await
+                    .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+
+            if sqlx::query!(
+                "UPDATE users SET gtc = ? WHERE id = ?",
                 setting,
-                *user.email
+                database_user.id
             )
             .execute(&self.pool)
             .await
             .is_err()
-        {
-            return Err(CommandError::Reply(format!("603 {tr_id}\r\n")));
+            {
+                return Err(CommandError::Reply(format!("603 {tr_id}\r\n")));
+            }
+
+            *version_number = list_version::bump(&self.pool, database_user.id)
+                .await
+                .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
         }
 
         Ok(vec![if protocol_version >= 10 {
             command.to_string()
         } else {
-            *version_number += 1;
             format!("GTC {tr_id} {version_number} {setting}\r\n")
         }])
     }
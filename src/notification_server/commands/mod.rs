@@ -5,10 +5,12 @@ pub mod blp;
 pub mod chg;
 pub mod cvr;
 pub mod fln;
+pub mod fnd;
 pub mod gcf;
 pub mod gtc;
 pub mod iln;
 pub mod nln;
+pub mod pag;
 pub mod prp;
 pub mod rea;
 pub mod reg;
@@ -16,6 +18,7 @@ pub mod rem;
 pub mod rmg;
 pub mod sbp;
 pub mod sdc;
+pub mod snd;
 pub mod syn;
 pub mod traits;
 pub mod ubx;
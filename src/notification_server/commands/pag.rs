@@ -0,0 +1,77 @@
+use super::traits::user_command::UserCommand;
+use crate::errors::command_error::CommandError;
+use crate::errors::page_error::PageError;
+use crate::mobile_pager::MobilePager;
+use crate::models::transient::authenticated_user::AuthenticatedUser;
+
+pub struct Pag {
+    mobile_pager: MobilePager,
+}
+
+impl Pag {
+    pub fn new(mobile_pager: MobilePager) -> Self {
+        Pag { mobile_pager }
+    }
+}
+
+impl UserCommand for Pag {
+    async fn handle(
+        &self,
+        protocol_version: u32,
+        command: &str,
+        user: &mut AuthenticatedUser,
+        version_number: &mut u32,
+    ) -> Result<Vec<String>, CommandError> {
+        let _ = protocol_version;
+        let _ = version_number;
+        let mut command_lines = command.lines();
+        let args: Vec<&str> = command_lines
+            .next()
+            .ok_or(CommandError::NoTrId)?
+            .split(' ')
+            .collect();
+
+        let tr_id = *args.get(1).ok_or(CommandError::NoTrId)?;
+        let email = *args
+            .get(2)
+            .ok_or(CommandError::Reply(format!("201 {tr_id}\r\n")))?;
+
+        let length = args
+            .get(3)
+            .unwrap_or(&"")
+            .parse()
+            .or(Err(CommandError::Reply(format!("201 {tr_id}\r\n"))))?;
+
+        let payload = command_lines.collect::<Vec<&str>>().join("\r\n");
+        let payload = payload.get(..length).unwrap_or(&payload);
+
+        let text = text(payload).ok_or(CommandError::Reply(format!("201 {tr_id}\r\n")))?;
+
+        self.mobile_pager
+            .page(&user.email, email, text)
+            .await
+            .map_err(|error| match error {
+                PageError::UserNotFound => CommandError::Reply(format!("208 {tr_id}\r\n")),
+                PageError::Unreachable => CommandError::Reply(format!("217 {tr_id}\r\n")),
+                PageError::LimitReached => CommandError::Reply(format!("800 {tr_id}\r\n")),
+                PageError::Database(_) => CommandError::Reply(format!("603 {tr_id}\r\n")),
+            })?;
+
+        Ok(vec![])
+    }
+}
+
+/// Gets the message out of a `<TEXT>` element, ignoring the locale and charset elements that follow it
+fn text(payload: &str) -> Option<String> {
+    let start = payload.find("<TEXT")?;
+    let payload = payload.get(start..)?;
+    let start = payload.find('>')? + 1;
+    let end = payload.find("</TEXT>")?;
+
+    let text = quick_xml::escape::unescape(payload.get(start..end)?).ok()?;
+    if text.trim().is_empty() {
+        return None;
+    }
+
+    Some(text.into_owned())
+}
@@ -1,16 +1,29 @@
 use super::traits::user_command::UserCommand;
 use crate::errors::command_error::CommandError;
+use crate::errors::contact_verification_error::ContactVerificationError;
+use crate::message::Message;
 use crate::models::transient::authenticated_user::AuthenticatedUser;
+use crate::notification_server::{display_name, list_version, verify_contact};
 use sqlx::{MySql, Pool};
 use std::sync::Arc;
+use tokio::sync::broadcast;
+
+/// Properties contacts see as BPR, in the order they are listed
+pub const CONTACT_PROPERTIES: [&str; 4] = ["PHH", "PHW", "PHM", "MOB"];
+
+/// Properties only the user sees, listed after the ones contacts see
+pub const OWN_PROPERTIES: [&str; 2] = ["MBE", "WWE"];
+
+const MAX_PHONE_NUMBER_LENGTH: usize = 100;
 
 pub struct Prp {
     pool: Pool<MySql>,
+    broadcast_tx: broadcast::Sender<Message>,
 }
 
 impl Prp {
-    pub fn new(pool: Pool<MySql>) -> Self {
-        Prp { pool }
+    pub fn new(pool: Pool<MySql>, broadcast_tx: broadcast::Sender<Message>) -> Self {
+        Prp { pool, broadcast_tx }
     }
 }
 
@@ -22,23 +35,26 @@ impl UserCommand for Prp {
         user: &mut AuthenticatedUser,
         version_number: &mut u32,
     ) -> Result<Vec<String>, CommandError> {
-        let _ = version_number;
         let args: Vec<&str> = command.trim().split(' ').collect();
 
         let tr_id = *args.get(1).ok_or(CommandError::NoTrId)?;
-        if protocol_version < 10 {
-            return Err(CommandError::Reply(format!("502 {tr_id}\r\n")));
-        }
-
         let parameter = *args
             .get(2)
             .ok_or(CommandError::Reply(format!("201 {tr_id}\r\n")))?;
 
-        let user_display_name = *args
-            .get(3)
-            .ok_or(CommandError::Reply(format!("201 {tr_id}\r\n")))?;
-
         if parameter == "MFN" {
+            if protocol_version < 10 {
+                return Err(CommandError::Reply(format!("502 {tr_id}\r\n")));
+            }
+
+            let user_display_name = *args
+                .get(3)
+                .ok_or(CommandError::Reply(format!("201 {tr_id}\r\n")))?;
+
+            if !display_name::is_valid(user_display_name) {
+                return Err(CommandError::Reply(format!("209 {tr_id}\r\n")));
+            }
+
             if sqlx::query!(
                 "UPDATE users SET display_name = ? WHERE email = ?",
                 user_display_name,
@@ -52,8 +68,109 @@ impl UserCommand for Prp {
             }
 
             user.display_name = Arc::new(user_display_name.to_string());
+            display_name::broadcast(&self.broadcast_tx, protocol_version, user);
+            return Ok(vec![command.to_string()]);
+        }
+
+        if !CONTACT_PROPERTIES.contains(&parameter) && !OWN_PROPERTIES.contains(&parameter) {
+            return Ok(vec![command.to_string()]);
+        }
+
+        // Leaving the value out clears the property
+        let value = args.get(3).copied();
+        if let Some(value) = value
+            && !is_valid(parameter, value)
+        {
+            return Err(CommandError::Reply(format!("201 {tr_id}\r\n")));
         }
 
-        Ok(vec![command.to_string()])
+        let database_user =
+            sqlx::query!("SELECT id FROM users WHERE email = ? LIMIT 1", *user.email)
+                .fetch_one(&self.pool)
+                .await
+                .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+
+        let result = if let Some(value) = value {
+            sqlx::query!(
+                "INSERT INTO user_properties (user_id, name, value) VALUES (?, ?, ?)
+                ON DUPLICATE KEY UPDATE value = ?",
+                database_user.id,
+                parameter,
+                value,
+                value
+            )
+            .execute(&self.pool)
+            .await
+        } else {
+            sqlx::query!(
+                "DELETE FROM user_properties WHERE user_id = ? AND name = ?",
+                database_user.id,
+                parameter
+            )
+            .execute(&self.pool)
+            .await
+        };
+
+        if result.is_err() {
+            return Err(CommandError::Reply(format!("603 {tr_id}\r\n")));
+        }
+
+        *version_number = list_version::bump(&self.pool, database_user.id)
+            .await
+            .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+
+        let property = match value {
+            Some(value) => format!("{parameter} {value}"),
+            None => parameter.to_string(),
+        };
+
+        if CONTACT_PROPERTIES.contains(&parameter) {
+            // Contacts that are offline get the change with their next SYN
+            list_version::bump_reverse_list(&self.pool, database_user.id)
+                .await
+                .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+
+            for (email, contact) in &user.contacts {
+                // Phone numbers are only shared with people the user added themselves, whatever their BLP
+                if !(contact.in_forward_list || contact.in_allow_list) {
+                    continue;
+                }
+
+                if matches!(
+                    verify_contact::verify_contact(user, email),
+                    Err(ContactVerificationError::ContactNotInAllowList
+                        | ContactVerificationError::ContactInBlockList)
+                ) {
+                    continue;
+                }
+
+                self.broadcast_tx
+                    .send(Message::ToContact {
+                        sender: user.email.clone(),
+                        receiver: email.clone(),
+                        message: format!("BPR 0 {} {property}\r\n", user.email),
+                    })
+                    .map_err(CommandError::CouldNotSendToBroadcast)?;
+            }
+        }
+
+        Ok(vec![if protocol_version >= 10 {
+            format!("PRP {tr_id} {property}\r\n")
+        } else {
+            format!("PRP {tr_id} {version_number} {property}\r\n")
+        }])
+    }
+}
+
+fn is_valid(parameter: &str, value: &str) -> bool {
+    match parameter {
+        "MOB" | "MBE" => value == "Y" || value == "N",
+        "WWE" => !value.is_empty() && value.len() <= 3 && value.chars().all(|c| c.is_ascii_digit()),
+        _ => {
+            value.len() <= MAX_PHONE_NUMBER_LENGTH
+                && urlencoding::decode(value).is_ok_and(|value| {
+                    !value.trim().is_empty() && !value.chars().any(char::is_control)
+                })
+        }
     }
 }
@@ -1,16 +1,20 @@
 use crate::errors::command_error::CommandError;
+use crate::message::Message;
 use crate::models::transient::authenticated_user::AuthenticatedUser;
 use crate::notification_server::commands::traits::user_command::UserCommand;
+use crate::notification_server::{display_name, list_version};
 use sqlx::{MySql, Pool};
 use std::sync::Arc;
+use tokio::sync::broadcast;
 
 pub struct Rea {
     pool: Pool<MySql>,
+    broadcast_tx: broadcast::Sender<Message>,
 }
 
 impl Rea {
-    pub fn new(pool: Pool<MySql>) -> Self {
-        Self { pool }
+    pub fn new(pool: Pool<MySql>, broadcast_tx: broadcast::Sender<Message>) -> Self {
+        Self { pool, broadcast_tx }
     }
 }
 
@@ -38,6 +42,10 @@ impl UserCommand for Rea {
             .map(|str| Arc::new(str.to_string()))
             .ok_or(CommandError::Reply(format!("201 {tr_id}\r\n")))?;
 
+        if !display_name::is_valid(&display_name) {
+            return Err(CommandError::Reply(format!("209 {tr_id}\r\n")));
+        }
+
         let database_user =
             sqlx::query!("SELECT id FROM users WHERE email = ? LIMIT 1", *user.email)
                 .fetch_one(&self.pool)
@@ -47,7 +55,7 @@ impl UserCommand for Rea {
         if email != *user.email {
             let contact = sqlx::query!(
                 "SELECT contacts.id FROM contacts INNER JOIN users ON contacts.contact_id = users.id
-                WHERE guid = ? AND user_id = ?
+                WHERE email = ? AND user_id = ?
                 LIMIT 1",
                 email,
                 database_user.id
@@ -86,9 +94,13 @@ impl UserCommand for Rea {
             }
 
             user.display_name = display_name.clone();
+            display_name::broadcast(&self.broadcast_tx, protocol_version, user);
         }
 
-        *version_number += 1;
+        *version_number = list_version::bump(&self.pool, database_user.id)
+            .await
+            .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+
         Ok(vec![format!(
             "REA {tr_id} {version_number} {email} {display_name}\r\n"
         )])
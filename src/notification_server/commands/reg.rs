@@ -1,6 +1,7 @@
 use super::traits::user_command::UserCommand;
 use crate::errors::command_error::CommandError;
 use crate::models::transient::authenticated_user::AuthenticatedUser;
+use crate::notification_server::list_version;
 use sqlx::{MySql, Pool};
 
 pub struct Reg {
@@ -71,10 +72,13 @@ impl UserCommand for Reg {
             .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
         }
 
+        *version_number = list_version::bump(&self.pool, database_user.id)
+            .await
+            .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+
         Ok(vec![if protocol_version >= 10 {
             format!("REG {tr_id} {group_id} {new_name}\r\n")
         } else {
-            *version_number += 1;
             format!("REG {tr_id} {version_number} {group_id} {new_name}\r\n")
         }])
     }
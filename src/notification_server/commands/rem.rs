@@ -4,6 +4,7 @@ use crate::message::Message;
 use crate::models::contact::Contact;
 use crate::models::transient::authenticated_user::AuthenticatedUser;
 use crate::notification_server::commands::nln;
+use crate::notification_server::{list_version, pending_additions};
 use sqlx::{MySql, Pool};
 use std::sync::Arc;
 use tokio::sync::broadcast;
@@ -96,6 +97,10 @@ impl UserCommand for Rem {
                         .await
                         .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
 
+                    *version_number = list_version::bump(&self.pool, database_user.id)
+                        .await
+                        .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+
                     Ok(vec![format!(
                         "REM {tr_id} {list} {contact_guid} {group_id}\r\n"
                     )])
@@ -135,7 +140,9 @@ impl UserCommand for Rem {
                         .await
                         .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
 
-                    *version_number += 1;
+                    *version_number = list_version::bump(&self.pool, database_user.id)
+                        .await
+                        .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
                     Ok(vec![format!(
                         "REM {tr_id} {list} {version_number} {contact_email} {group_id}\r\n"
                     )])
@@ -177,10 +184,23 @@ impl UserCommand for Rem {
                     contact.in_forward_list = false;
                 };
 
+                *version_number = list_version::bump(&self.pool, database_user.id)
+                    .await
+                    .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+
+                // The contact's reverse list changed as well
+                list_version::bump(&self.pool, contact.contact_id)
+                    .await
+                    .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+
+                pending_additions::resolve(&self.pool, contact.contact_id, database_user.id)
+                    .await
+                    .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+
                 let reply = Message::ToContact {
                     sender: user.email.clone(),
                     receiver: Arc::new(contact.email),
-                    message: convert(protocol_version, user, version_number),
+                    message: convert(user),
                 };
 
                 self.broadcast_tx
@@ -224,17 +244,29 @@ impl UserCommand for Rem {
                     contact.in_forward_list = false;
                 };
 
+                *version_number = list_version::bump(&self.pool, database_user.id)
+                    .await
+                    .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+
+                // The contact's reverse list changed as well
+                list_version::bump(&self.pool, contact.contact_id)
+                    .await
+                    .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+
+                pending_additions::resolve(&self.pool, contact.contact_id, database_user.id)
+                    .await
+                    .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+
                 let reply = Message::ToContact {
                     sender: user.email.clone(),
                     receiver: Arc::new(contact.email),
-                    message: convert(protocol_version, user, version_number),
+                    message: convert(user),
                 };
 
                 self.broadcast_tx
                     .send(reply)
                     .map_err(CommandError::CouldNotSendToBroadcast)?;
 
-                *version_number += 1;
                 Ok(vec![format!(
                     "REM {tr_id} {list} {version_number} {contact_email}\r\n"
                 )])
@@ -295,6 +327,14 @@ impl UserCommand for Rem {
                     contact.in_block_list = false;
                 };
 
+                self.broadcast_tx
+                    .send(Message::SetBlocked {
+                        email: user.email.clone(),
+                        contact: contact_email.clone(),
+                        blocked: false,
+                    })
+                    .map_err(CommandError::CouldNotSendToBroadcast)?;
+
                 let nln_command = nln::convert(protocol_version, user)
                     .map_err(CommandError::CouldNotCreateNln)?;
                 let thread_message = Message::ToContact {
@@ -308,10 +348,13 @@ impl UserCommand for Rem {
                     .map_err(CommandError::CouldNotSendToBroadcast)?;
             }
 
+            *version_number = list_version::bump(&self.pool, database_user.id)
+                .await
+                .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+
             if protocol_version >= 10 {
                 Ok(vec![format!("REM {tr_id} {list} {contact_email}\r\n")])
             } else {
-                *version_number += 1;
                 Ok(vec![format!(
                     "REM {tr_id} {list} {version_number} {contact_email}\r\n"
                 )])
@@ -320,17 +363,8 @@ impl UserCommand for Rem {
     }
 }
 
-pub fn convert(
-    protocol_version: u32,
-    user: &AuthenticatedUser,
-    version_number: &mut u32,
-) -> String {
+/// The receiving session puts in its own list version for clients that expect one
+pub fn convert(user: &AuthenticatedUser) -> String {
     let user_email = &user.email;
-
-    if protocol_version >= 10 {
-        format!("REM 0 RL N={user_email}\r\n")
-    } else {
-        *version_number += 1;
-        format!("REM 0 RL {version_number} {user_email}\r\n")
-    }
+    format!("REM 0 RL N={user_email}\r\n")
 }
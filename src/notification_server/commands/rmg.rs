@@ -1,6 +1,7 @@
 use super::traits::user_command::UserCommand;
 use crate::errors::command_error::CommandError;
 use crate::models::transient::authenticated_user::AuthenticatedUser;
+use crate::notification_server::list_version;
 use sqlx::{MySql, Pool};
 
 pub struct Rmg {
@@ -31,6 +32,12 @@ impl UserCommand for Rmg {
             return Err(CommandError::Reply(format!("230 {tr_id}\r\n")));
         }
 
+        let database_user =
+            sqlx::query!("SELECT id FROM users WHERE email = ? LIMIT 1", *user.email)
+                .fetch_one(&self.pool)
+                .await
+                .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+
         if protocol_version >= 10 {
             let group = sqlx::query!(
                 "SELECT groups.id, user_id, name, groups.guid FROM groups
@@ -56,6 +63,18 @@ impl UserCommand for Rmg {
                 .await
                 .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
         } else {
+            if sqlx::query!(
+                "SELECT id FROM groups WHERE id = ? AND user_id = ? LIMIT 1",
+                group_id,
+                database_user.id
+            )
+            .fetch_one(&self.pool)
+            .await
+            .is_err()
+            {
+                return Err(CommandError::Reply(format!("224 {tr_id}\r\n")));
+            }
+
             if sqlx::query!("SELECT id FROM group_members WHERE group_id = ?", group_id)
                 .fetch_one(&self.pool)
                 .await
@@ -70,10 +89,13 @@ impl UserCommand for Rmg {
                 .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
         }
 
+        *version_number = list_version::bump(&self.pool, database_user.id)
+            .await
+            .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+
         Ok(vec![if protocol_version >= 10 {
             format!("RMG {tr_id} 1 {group_id}\r\n")
         } else {
-            *version_number += 1;
             format!("RMG {tr_id} {version_number} {group_id}\r\n")
         }])
     }
@@ -1,6 +1,7 @@
 use super::traits::user_command::UserCommand;
 use crate::errors::command_error::CommandError;
 use crate::models::transient::authenticated_user::AuthenticatedUser;
+use crate::notification_server::{display_name, list_version};
 use sqlx::{MySql, Pool};
 use std::sync::Arc;
 
@@ -22,7 +23,6 @@ impl UserCommand for Sbp {
         user: &mut AuthenticatedUser,
         version_number: &mut u32,
     ) -> Result<Vec<String>, CommandError> {
-        let _ = version_number;
         let args: Vec<&str> = command.trim().split(' ').collect();
 
         let tr_id = *args.get(1).ok_or(CommandError::NoTrId)?;
@@ -44,6 +44,10 @@ impl UserCommand for Sbp {
             .ok_or(CommandError::Reply(format!("201 {tr_id}\r\n")))?;
 
         if parameter == "MFN" {
+            if !display_name::is_valid(&contact_display_name) {
+                return Err(CommandError::Reply(format!("209 {tr_id}\r\n")));
+            }
+
             let database_user = sqlx::query!(
                 "SELECT id, email, password, display_name, puid, guid, gtc, blp 
                 FROM users WHERE email = ? LIMIT 1",
@@ -76,6 +80,10 @@ impl UserCommand for Sbp {
                 return Err(CommandError::Reply(format!("603 {tr_id}\r\n")));
             }
 
+            *version_number = list_version::bump(&self.pool, database_user.id)
+                .await
+                .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+
             if let Some(contact) = user.contacts.get_mut(&contact.email) {
                 contact.display_name = contact_display_name;
             };
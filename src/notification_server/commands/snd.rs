@@ -0,0 +1,53 @@
+use super::traits::user_command::UserCommand;
+use crate::email_invitations::EmailInviter;
+use crate::errors::command_error::CommandError;
+use crate::errors::email_invitation_error::EmailInvitationError;
+use crate::models::transient::authenticated_user::AuthenticatedUser;
+
+pub struct Snd {
+    email_inviter: EmailInviter,
+}
+
+impl Snd {
+    pub fn new(email_inviter: EmailInviter) -> Self {
+        Snd { email_inviter }
+    }
+}
+
+impl UserCommand for Snd {
+    async fn handle(
+        &self,
+        protocol_version: u32,
+        command: &str,
+        user: &mut AuthenticatedUser,
+        version_number: &mut u32,
+    ) -> Result<Vec<String>, CommandError> {
+        let _ = protocol_version;
+        let _ = version_number;
+        let args: Vec<&str> = command.trim().split(' ').collect();
+
+        let tr_id = *args.get(1).ok_or(CommandError::NoTrId)?;
+        let email = *args
+            .get(2)
+            .ok_or(CommandError::Reply(format!("201 {tr_id}\r\n")))?;
+
+        match self.email_inviter.send(&user.email, email).await {
+            // Nothing to invite them to, the client can add them instead
+            Ok(()) | Err(EmailInvitationError::AlreadyRegistered) => {}
+            Err(EmailInvitationError::InvalidAddress) => {
+                return Err(CommandError::Reply(format!("201 {tr_id}\r\n")));
+            }
+            Err(EmailInvitationError::InviterNotVerified) => {
+                return Err(CommandError::Reply(format!("502 {tr_id}\r\n")));
+            }
+            Err(EmailInvitationError::LimitReached) => {
+                return Err(CommandError::Reply(format!("800 {tr_id}\r\n")));
+            }
+            Err(EmailInvitationError::Database(_)) => {
+                return Err(CommandError::Reply(format!("603 {tr_id}\r\n")));
+            }
+        }
+
+        Ok(vec![format!("SND {tr_id} {email}\r\n")])
+    }
+}
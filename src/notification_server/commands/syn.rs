@@ -1,10 +1,12 @@
+use super::prp;
 use super::traits::user_command::UserCommand;
 use crate::errors::command_error::CommandError;
-use crate::models::contact::Contact;
 use crate::models::group::Group;
 use crate::models::transient::authenticated_user::AuthenticatedUser;
 use crate::models::transient::transient_contact::TransientContact;
+use crate::notification_server::{list_version, pending_additions};
 use sqlx::{MySql, Pool};
+use std::collections::HashMap;
 use std::sync::Arc;
 
 pub struct Syn {
@@ -29,7 +31,7 @@ impl UserCommand for Syn {
         let tr_id = *args.get(1).ok_or(CommandError::NoTrId)?;
 
         let database_user = sqlx::query!(
-            "SELECT id, display_name, gtc, blp
+            "SELECT id, display_name, gtc, blp, list_version, list_updated_at
                 FROM users WHERE email = ? LIMIT 1",
             *user.email
         )
@@ -49,6 +51,20 @@ impl UserCommand for Syn {
             responses.push(format!("PRP MFN {}\r\n", user.display_name));
         }
 
+        let properties = sqlx::query!(
+            "SELECT name, value FROM user_properties WHERE user_id = ?",
+            database_user.id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+
+        for name in prp::CONTACT_PROPERTIES.iter().chain(&prp::OWN_PROPERTIES) {
+            if let Some(property) = properties.iter().find(|property| property.name == *name) {
+                responses.push(format!("PRP {name} {}\r\n", property.value));
+            }
+        }
+
         let user_groups = sqlx::query_as!(
             Group,
             "SELECT id, user_id, name, guid FROM groups WHERE user_id = ?",
@@ -67,20 +83,64 @@ impl UserCommand for Syn {
             });
         }
 
-        let user_contacts = sqlx::query_as!(
-            Contact,
-            "SELECT contacts.id, user_id, contact_id, contacts.display_name, email, guid,
-                in_forward_list as `in_forward_list: _`,
-                in_allow_list as `in_allow_list: _`,
-                in_block_list as `in_block_list: _`
+        // The reverse list and whether each contact lets the user see their phone numbers come from the same query
+        let user_contacts = sqlx::query!(
+            "SELECT contacts.id, contacts.contact_id, contacts.display_name, users.email, users.guid, users.blp,
+                contacts.in_forward_list as `in_forward_list: bool`,
+                contacts.in_allow_list as `in_allow_list: bool`,
+                contacts.in_block_list as `in_block_list: bool`,
+                reverse_contacts.in_forward_list as `reverse_in_forward_list: bool`,
+                reverse_contacts.in_allow_list as `reverse_in_allow_list: bool`,
+                reverse_contacts.in_block_list as `reverse_in_block_list: bool`
                 FROM contacts INNER JOIN users ON contacts.contact_id = users.id
-                WHERE user_id = ?",
+                LEFT JOIN contacts reverse_contacts ON reverse_contacts.user_id = contacts.contact_id
+                AND reverse_contacts.contact_id = contacts.user_id
+                WHERE contacts.user_id = ?",
             database_user.id
         )
         .fetch_all(&self.pool)
         .await
         .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
 
+        let contact_properties: HashMap<(i32, String), String> = sqlx::query!(
+            "SELECT user_properties.user_id, name, value FROM user_properties
+                INNER JOIN contacts ON user_properties.user_id = contacts.contact_id
+                WHERE contacts.user_id = ? AND contacts.in_forward_list = TRUE",
+            database_user.id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?
+        .into_iter()
+        .map(|property| ((property.user_id, property.name), property.value))
+        .collect();
+
+        let group_members = sqlx::query!(
+            "SELECT group_members.group_id, group_members.contact_id FROM group_members
+                INNER JOIN groups ON group_members.group_id = groups.id
+                WHERE groups.user_id = ?",
+            database_user.id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?;
+
+        let mut contact_groups: HashMap<i32, Vec<String>> = HashMap::new();
+        for member in group_members {
+            let Some(group) = user_groups.iter().find(|group| group.id == member.group_id) else {
+                continue;
+            };
+
+            contact_groups
+                .entry(member.contact_id)
+                .or_default()
+                .push(if protocol_version >= 10 {
+                    group.guid.clone()
+                } else {
+                    group.id.to_string()
+                });
+        }
+
         let number_of_contacts = user_contacts.len();
         for contact in user_contacts {
             let mut listbit = 0;
@@ -96,6 +156,10 @@ impl UserCommand for Syn {
                 listbit += 4;
             }
 
+            if contact.reverse_in_forward_list == Some(true) {
+                listbit += 8;
+            }
+
             let display_name = Arc::new(contact.display_name);
             let contact_email = Arc::new(contact.email);
 
@@ -112,19 +176,6 @@ impl UserCommand for Syn {
             user.contacts
                 .insert(transient_contact.email.clone(), transient_contact);
 
-            // Make reverse list
-            if sqlx::query!(
-                "SELECT id FROM contacts WHERE user_id = ? AND contact_id = ? AND in_forward_list = TRUE LIMIT 1",
-                contact.id,
-                database_user.id
-            )
-            .fetch_one(&self.pool)
-            .await
-            .is_ok()
-            {
-                listbit += 8;
-            }
-
             if !contact.in_forward_list {
                 responses.push(if protocol_version >= 12 {
                     // Only the Windows Live type is supported at the moment
@@ -139,32 +190,10 @@ impl UserCommand for Syn {
             }
 
             let guid = contact.guid;
-            let mut group_list = String::from("");
-
-            for group in &user_groups {
-                if sqlx::query!(
-                    "SELECT id FROM group_members WHERE group_id = ? AND contact_id = ? LIMIT 1",
-                    group.id,
-                    contact.id
-                )
-                .fetch_one(&self.pool)
-                .await
-                .is_ok()
-                {
-                    group_list.push_str(
-                        if protocol_version >= 10 {
-                            format!("{},", group.guid)
-                        } else {
-                            format!("{},", group.id)
-                        }
-                        .as_str(),
-                    );
-                }
-            }
-
-            if let Some(list) = group_list.strip_suffix(",") {
-                group_list = list.to_string();
-            }
+            let group_list = contact_groups
+                .get(&contact.id)
+                .map(|groups| groups.join(","))
+                .unwrap_or_default();
 
             responses.push(if protocol_version >= 12 {
                 // Only the Windows Live type is supported at the moment
@@ -178,8 +207,25 @@ impl UserCommand for Syn {
             } else {
                 format!("LST {contact_email} {display_name} {listbit} {group_list}\r\n")
             });
+
+            // Phone numbers are only shared with people the contact added themselves, whatever their BLP
+            let shows_properties = contact.reverse_in_block_list != Some(true)
+                && (contact.reverse_in_forward_list == Some(true)
+                    || contact.reverse_in_allow_list == Some(true));
+
+            if !shows_properties {
+                continue;
+            }
+
+            for name in prp::CONTACT_PROPERTIES {
+                if let Some(value) = contact_properties.get(&(contact.contact_id, name.to_string()))
+                {
+                    responses.push(format!("BPR {name} {value}\r\n"));
+                }
+            }
         }
 
+        // The contacts are still loaded above, but a client whose cached list is current only gets the header
         if protocol_version >= 10 {
             let first_timestamp = *args
                 .get(2)
@@ -189,7 +235,12 @@ impl UserCommand for Syn {
                 .get(3)
                 .ok_or(CommandError::Reply(format!("201 {tr_id}\r\n")))?;
 
-            responses.insert(0, format!("SYN {tr_id} {first_timestamp} {second_timestamp} {number_of_contacts} {number_of_groups}\r\n"));
+            let timestamp = list_version::timestamp(database_user.list_updated_at);
+            if first_timestamp == timestamp && second_timestamp == timestamp {
+                responses = vec![format!("SYN {tr_id} {timestamp} {timestamp}\r\n")];
+            } else {
+                responses.insert(0, format!("SYN {tr_id} {timestamp} {timestamp} {number_of_contacts} {number_of_groups}\r\n"));
+            }
         } else {
             let client_version_number = args
                 .get(2)
@@ -197,13 +248,31 @@ impl UserCommand for Syn {
                 .parse::<u32>()
                 .or(Err(CommandError::Reply(format!("201 {tr_id}\r\n"))))?;
 
-            *version_number = client_version_number + 1;
-            responses.insert(
-                0,
-                format!("SYN {tr_id} {version_number} {number_of_contacts} {number_of_groups}\r\n"),
-            );
+            *version_number = database_user.list_version;
+            if client_version_number == *version_number {
+                responses = vec![format!("SYN {tr_id} {version_number}\r\n")];
+            } else {
+                responses.insert(
+                    0,
+                    format!(
+                        "SYN {tr_id} {version_number} {number_of_contacts} {number_of_groups}\r\n"
+                    ),
+                );
+            }
         }
 
+        // Clients only ask about additions that show up in their reverse list while they're online
+        responses.extend(
+            pending_additions::convert(
+                &self.pool,
+                protocol_version,
+                *version_number,
+                database_user.id,
+            )
+            .await
+            .or(Err(CommandError::Reply(format!("603 {tr_id}\r\n"))))?,
+        );
+
         Ok(responses)
     }
 }
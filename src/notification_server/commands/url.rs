@@ -1,8 +1,17 @@
 use super::traits::command::Command;
+use crate::config::Config;
 use crate::errors::command_error::CommandError;
-use std::env;
+use std::sync::Arc;
 
-pub struct Url;
+pub struct Url {
+    config: Arc<Config>,
+}
+
+impl Url {
+    pub fn new(config: Arc<Config>) -> Self {
+        Url { config }
+    }
+}
 
 impl Command for Url {
     async fn handle(
@@ -14,8 +23,7 @@ impl Command for Url {
 
         let args: Vec<&str> = command.trim().split(' ').collect();
         let tr_id = *args.get(1).ok_or(CommandError::NoTrId)?;
-        let server_name =
-            env::var("SERVER_DOMAIN").or(Err(CommandError::Reply(format!("500 {tr_id}\r\n"))))?;
+        let server_name = &self.config.http.server_domain;
 
         Ok(vec![format!(
             "URL {tr_id} /url https://{server_name}/url 1\r\n"
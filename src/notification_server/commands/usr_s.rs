@@ -1,23 +1,32 @@
 use super::traits::authentication_command::AuthenticationCommand;
+use crate::config::Config;
 use crate::errors::command_error::CommandError;
 use crate::message::Message;
 use crate::models::transient::authenticated_user::AuthenticatedUser;
 use crate::models::user::User;
+use crate::tokens;
 use chrono::Utc;
 use sqlx::{MySql, Pool};
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::sync::broadcast;
 
 pub struct UsrS {
     pool: Pool<MySql>,
+    config: Arc<Config>,
+    peer_address: SocketAddr,
 }
 
 impl UsrS {
-    pub fn new(pool: Pool<MySql>) -> Self {
-        UsrS { pool }
+    pub fn new(pool: Pool<MySql>, config: Arc<Config>, peer_address: SocketAddr) -> Self {
+        UsrS {
+            pool,
+            config,
+            peer_address,
+        }
     }
 
-    fn get_hotmail_options(user: &User) -> String {
+    fn get_hotmail_options(user: &User, peer_address: &SocketAddr) -> String {
         let mut payload = String::from("MIME-Version: 1.0\r\n");
         let timestamp = Utc::now().timestamp();
 
@@ -43,8 +52,8 @@ impl UsrS {
         payload.push_str("Flags: 1027\r\n");
         payload.push_str("sid: 507\r\n");
         payload.push_str("MSPAuth: \r\n");
-        payload.push_str("ClientIP: 24.111.111.111\r\n");
-        payload.push_str("ClientPort: 60712\r\n");
+        payload.push_str(format!("ClientIP: {}\r\n", peer_address.ip().to_canonical()).as_str());
+        payload.push_str(format!("ClientPort: {}\r\n", peer_address.port()).as_str());
         payload.push_str("ABCHMigrated: 1\r\n\r\n");
 
         let length = payload.len();
@@ -68,8 +77,8 @@ impl AuthenticationCommand for UsrS {
             .ok_or(CommandError::Reply(format!("201 {tr_id}\r\n")))?;
 
         let token = sqlx::query!(
-            "SELECT token, valid_until, user_id FROM tokens WHERE token = ? LIMIT 1",
-            email.trim()
+            "SELECT id, valid_until, user_id FROM tokens WHERE token_hash = ? LIMIT 1",
+            tokens::hash(&self.config.tokens.secret, email.trim())
         )
         .fetch_one(&self.pool)
         .await
@@ -91,6 +100,7 @@ impl AuthenticationCommand for UsrS {
                 .map_err(CommandError::CouldNotSendToBroadcast)?;
 
             let mut authenticated_user = AuthenticatedUser::new(database_user.email.clone());
+            authenticated_user.token_id = Some(token.id);
             let thread_message = Message::ToContact {
                 sender: database_user.email.clone(),
                 receiver: database_user.email.clone(),
@@ -101,7 +111,7 @@ impl AuthenticationCommand for UsrS {
                 .send(thread_message)
                 .map_err(CommandError::CouldNotSendToBroadcast)?;
 
-            let (tx, _) = broadcast::channel::<Message>(16);
+            let (tx, _) = broadcast::channel::<Message>(self.config.channels.contact_capacity);
             broadcast_tx
                 .send(Message::SetTx {
                     key: database_user.email.clone(),
@@ -110,7 +120,7 @@ impl AuthenticationCommand for UsrS {
                 .map_err(CommandError::CouldNotSendToBroadcast)?;
 
             let contact_rx = tx.subscribe();
-            let hotmail_options = Self::get_hotmail_options(&database_user);
+            let hotmail_options = Self::get_hotmail_options(&database_user, &self.peer_address);
 
             let mut replies = vec![
                 if protocol_version >= 10 {
@@ -1,18 +1,15 @@
 use super::traits::user_command::UserCommand;
 use crate::errors::command_error::CommandError;
-use crate::{
-    message::Message, models::transient::authenticated_user::AuthenticatedUser,
-    switchboard::session::Session,
-};
+use crate::{message::Message, models::transient::authenticated_user::AuthenticatedUser};
 use argon2::password_hash::rand_core::{OsRng, RngCore};
 use rand::distr::SampleString;
 use rand_distr::Alphanumeric;
-use std::collections::HashMap;
-use std::{
-    env,
-    sync::{Arc, Mutex},
-};
-use tokio::sync::broadcast;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::time::timeout;
+
+const ASSIGNMENT_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub struct Xfr {
     broadcast_tx: broadcast::Sender<Message>,
@@ -53,29 +50,37 @@ impl UserCommand for Xfr {
             return Err(CommandError::Reply(format!("913 {tr_id}\r\n")));
         }
 
-        let switchboard_ip =
-            env::var("SWITCHBOARD_IP").or(Err(CommandError::Reply(format!("500 {tr_id}\r\n"))))?;
-
         let cki_string = Arc::new(Alphanumeric.sample_string(&mut rand::rng(), 16));
-        let (tx, _) = broadcast::channel::<Message>(16);
         let session_id = Arc::new(format!("{:08}", OsRng.next_u32()));
-
-        let session = Session {
-            session_id,
-            cki_string: cki_string.clone(),
-            session_tx: tx,
-            principals: Arc::new(Mutex::new(HashMap::new())),
-        };
+        let mut broadcast_rx = self.broadcast_tx.subscribe();
 
         self.broadcast_tx
-            .send(Message::SetSession {
-                key: cki_string.clone(),
-                value: session,
+            .send(Message::AssignSession {
+                session_id,
+                cki_string: cki_string.clone(),
+                email: user.email.clone(),
             })
             .map_err(CommandError::CouldNotSendToBroadcast)?;
 
+        let assigned = timeout(ASSIGNMENT_TIMEOUT, async {
+            loop {
+                match broadcast_rx.recv().await {
+                    Ok(Message::SessionAssigned { key, address }) if key == cki_string => {
+                        return Ok(address);
+                    }
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(err) => return Err(CommandError::CouldNotReceiveFromBroadcast(err)),
+                }
+            }
+        })
+        .await;
+
+        let Some(switchboard_address) = assigned.unwrap_or(Ok(None))? else {
+            return Err(CommandError::Reply(format!("601 {tr_id}\r\n")));
+        };
+
         Ok(vec![format!(
-            "XFR {tr_id} SB {switchboard_ip}:1864 CKI {cki_string}\r\n"
+            "XFR {tr_id} SB {switchboard_address} CKI {cki_string}\r\n"
         )])
     }
 }
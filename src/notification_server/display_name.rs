@@ -0,0 +1,49 @@
+use crate::message::Message;
+use crate::models::transient::authenticated_user::AuthenticatedUser;
+use crate::notification_server::commands::nln;
+use crate::notification_server::verify_contact;
+use tokio::sync::broadcast;
+
+/// Length of the display name columns, which store names URL-encoded
+const MAX_LENGTH: usize = 150;
+
+pub fn is_valid(display_name: &str) -> bool {
+    if display_name.is_empty() || display_name.len() > MAX_LENGTH {
+        return false;
+    }
+
+    let Ok(decoded) = urlencoding::decode(display_name) else {
+        return false;
+    };
+
+    !decoded.trim().is_empty() && !decoded.chars().any(char::is_control)
+}
+
+/// Sends the user's new display name to contacts that can see them and to their switchboard sessions
+pub fn broadcast(
+    broadcast_tx: &broadcast::Sender<Message>,
+    protocol_version: u32,
+    user: &AuthenticatedUser,
+) {
+    let _ = broadcast_tx.send(Message::SetDisplayName {
+        email: user.email.clone(),
+        display_name: user.display_name.clone(),
+    });
+
+    // Users appearing offline have nothing to announce
+    let Ok(nln_command) = nln::convert(protocol_version, user) else {
+        return;
+    };
+
+    for email in user.contacts.keys() {
+        if verify_contact::verify_contact(user, email).is_err() {
+            continue;
+        }
+
+        let _ = broadcast_tx.send(Message::ToContact {
+            sender: user.email.clone(),
+            receiver: email.clone(),
+            message: nln_command.clone(),
+        });
+    }
+}
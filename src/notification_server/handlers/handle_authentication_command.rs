@@ -1,4 +1,5 @@
 use super::process_command::{process_authentication_command, process_command};
+use crate::config::Config;
 use crate::errors::command_error::CommandError;
 use crate::{
     message::Message,
@@ -8,12 +9,16 @@ use crate::{
 use log::{error, trace, warn};
 use sqlx::{MySql, Pool};
 use std::error;
+use std::net::SocketAddr;
+use std::sync::Arc;
 use tokio::{io::AsyncWriteExt, net::tcp::WriteHalf, sync::broadcast};
 
 pub async fn handle_authentication_command(
     protocol_version: u32,
     pool: &Pool<MySql>,
     broadcast_tx: &broadcast::Sender<Message>,
+    config: &Arc<Config>,
+    peer_address: &SocketAddr,
     wr: &mut WriteHalf<'_>,
     command: Vec<u8>,
 ) -> Result<
@@ -26,7 +31,8 @@ pub async fn handle_authentication_command(
     match *args.first().unwrap_or(&"") {
         "CVR" => {
             trace!("C: {command}");
-            process_command(protocol_version, wr, &Cvr, command).await?;
+            let cvr = Cvr::new(config.clone());
+            process_command(protocol_version, wr, &cvr, command).await?;
         }
 
         "USR" => match *args.get(3).unwrap_or(&"") {
@@ -46,7 +52,7 @@ pub async fn handle_authentication_command(
                     args[0], args[1], args[2], args[3]
                 );
 
-                let usr = UsrS::new(pool.clone());
+                let usr = UsrS::new(pool.clone(), config.clone(), *peer_address);
                 return process_authentication_command(
                     protocol_version,
                     wr,
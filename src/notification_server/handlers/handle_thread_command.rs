@@ -1,4 +1,5 @@
 use crate::errors::thread_command_error::ThreadCommandError;
+use crate::models::transient::transient_contact::TransientContact;
 use crate::notification_server::commands::{iln, nln, ubx};
 use crate::notification_server::verify_contact;
 use crate::{message::Message, models::transient::authenticated_user::AuthenticatedUser};
@@ -178,12 +179,12 @@ pub async fn handle_thread_command(
                     display_name.drain(..2);
                 }
 
+                *version_number += 1;
                 let command = format!(
                     "ADD {} {} {version_number} {email} {display_name}\r\n",
                     args[1], args[2]
                 );
 
-                *version_number += 1;
                 wr.write_all(command.as_bytes()).await?;
                 trace!("S: {command}");
             }
@@ -191,6 +192,24 @@ pub async fn handle_thread_command(
 
         "ADD" => {
             trace!("Thread {sender}: {command}");
+            if args.len() < 6 {
+                return Err(ThreadCommandError::NotEnoughArguments(command).into());
+            }
+
+            // The version in the message is a placeholder for the receiving session's own
+            let command = if protocol_version <= 9 {
+                *version_number += 1;
+                format!(
+                    "ADD {} {} {version_number} {} {}\r\n",
+                    args[1], args[2], args[4], args[5]
+                )
+            } else {
+                format!(
+                    "ADC {} {} N={} F={}\r\n",
+                    args[1], args[2], args[4], args[5]
+                )
+            };
+
             if verify_contact::verify_contact(authenticated_user, &sender).is_err() {
                 wr.write_all(command.as_bytes()).await?;
 
@@ -209,20 +228,8 @@ pub async fn handle_thread_command(
             };
 
             broadcast_tx.send(thread_message)?;
-
-            if protocol_version <= 9 || args.len() < 6 {
-                wr.write_all(command.as_bytes()).await?;
-                trace!("S: {command}");
-            } else {
-                let command = format!(
-                    "ADC {} {} N={} F={}\r\n",
-                    args[1], args[2], args[4], args[5]
-                );
-
-                *version_number += 1;
-                wr.write_all(command.as_bytes()).await?;
-                trace!("S: {command}");
-            }
+            wr.write_all(command.as_bytes()).await?;
+            trace!("S: {command}");
         }
 
         "REM" => {
@@ -231,17 +238,46 @@ pub async fn handle_thread_command(
                 wr.write_all(command.as_bytes()).await?;
                 trace!("S: {command}");
             } else {
+                *version_number += 1;
                 let command = format!(
                     "{} {} {} {version_number} {}\r\n",
-                    args[0], args[1], args[2], args[3]
+                    args[0],
+                    args[1],
+                    args[2],
+                    args[3].trim_start_matches("N=")
                 );
 
-                *version_number += 1;
                 wr.write_all(command.as_bytes()).await?;
                 trace!("S: {command}");
             }
         }
 
+        "BPR" => {
+            trace!("Thread {sender}: {command}");
+            if args.len() < 4 {
+                return Err(ThreadCommandError::NotEnoughArguments(command).into());
+            }
+
+            if !authenticated_user
+                .contacts
+                .get(&sender)
+                .is_some_and(|contact| contact.in_forward_list)
+            {
+                return Ok(());
+            }
+
+            let property = args[3..].join(" ");
+            let command = if protocol_version >= 10 {
+                format!("BPR {sender} {property}\r\n")
+            } else {
+                *version_number += 1;
+                format!("BPR {version_number} {sender} {property}\r\n")
+            };
+
+            wr.write_all(command.as_bytes()).await?;
+            trace!("S: {command}");
+        }
+
         "RNG" => {
             if args.len() < 7 {
                 return Err(ThreadCommandError::NotEnoughArguments(command).into());
@@ -269,6 +305,141 @@ pub async fn handle_thread_command(
             return Err(ThreadCommandError::UserLoggedInOnAnotherComputer.into());
         }
 
+        "RevokeToken" => {
+            trace!("Thread {sender}: {command}");
+            let token_id = args
+                .get(1)
+                .and_then(|token_id| token_id.parse::<i32>().ok());
+
+            if token_id.is_some() && token_id == authenticated_user.token_id {
+                let reply = "OUT\r\n";
+                wr.write_all(reply.as_bytes()).await?;
+
+                trace!("S: {reply}");
+                return Err(ThreadCommandError::TokenRevoked.into());
+            }
+        }
+
+        "DeleteAccount" => {
+            trace!("Thread {sender}: {command}");
+            if sender == authenticated_user.email {
+                let reply = "OUT\r\n";
+                wr.write_all(reply.as_bytes()).await?;
+
+                trace!("S: {reply}");
+                return Err(ThreadCommandError::AccountDeleted.into());
+            }
+        }
+
+        "PasswordReset" => {
+            trace!("Thread {sender}: {command}");
+            if sender == authenticated_user.email {
+                let reply = "OUT\r\n";
+                wr.write_all(reply.as_bytes()).await?;
+
+                trace!("S: {reply}");
+                return Err(ThreadCommandError::PasswordReset.into());
+            }
+        }
+
+        "RemoveContact" => {
+            // A contact deleted their account
+            trace!("Thread {sender}: {command}");
+            if args.len() < 3 {
+                return Err(ThreadCommandError::NotEnoughArguments(command).into());
+            }
+
+            let contact_guid = args[1];
+            let mut replies = Vec::new();
+
+            // The deletion bumped the list version once, so every line shares it
+            if protocol_version < 10 {
+                *version_number += 1;
+            }
+
+            if let Some(contact) = authenticated_user.contacts.remove(&sender) {
+                // Signed out first, so clients don't keep showing them online
+                if contact.presence.is_some() {
+                    replies.push(format!("FLN {sender}\r\n"));
+                }
+
+                for (list, in_list) in [
+                    ("FL", contact.in_forward_list),
+                    ("AL", contact.in_allow_list),
+                    ("BL", contact.in_block_list),
+                ] {
+                    if !in_list {
+                        continue;
+                    }
+
+                    replies.push(if protocol_version >= 10 {
+                        if list == "FL" {
+                            format!("REM 0 FL {contact_guid}\r\n")
+                        } else {
+                            format!("REM 0 {list} {sender}\r\n")
+                        }
+                    } else {
+                        format!("REM 0 {list} {version_number} {sender}\r\n")
+                    });
+                }
+            }
+
+            if args[2] == "1" {
+                replies.push(if protocol_version >= 10 {
+                    format!("REM 0 RL N={sender}\r\n")
+                } else {
+                    format!("REM 0 RL {version_number} {sender}\r\n")
+                });
+            }
+
+            for reply in replies {
+                wr.write_all(reply.as_bytes()).await?;
+                trace!("S: {reply}");
+            }
+        }
+
+        "AddContact" => {
+            // Someone registered with this user's invitation and is now on their forward, allow and reverse lists
+            trace!("Thread {sender}: {command}");
+            if args.len() < 2 {
+                return Err(ThreadCommandError::NotEnoughArguments(command).into());
+            }
+
+            let contact_guid = args[1];
+            authenticated_user.contacts.insert(
+                sender.clone(),
+                TransientContact {
+                    email: sender.clone(),
+                    display_name: sender.clone(),
+                    presence: None,
+                    msn_object: None,
+                    in_forward_list: true,
+                    in_allow_list: true,
+                    in_block_list: false,
+                },
+            );
+
+            // The registration bumped the list version once, so every line shares it
+            let replies = if protocol_version >= 10 {
+                vec![
+                    format!("ADC 0 FL N={sender} F={sender} C={contact_guid}\r\n"),
+                    format!("ADC 0 AL N={sender}\r\n"),
+                    format!("ADC 0 RL N={sender} F={sender}\r\n"),
+                ]
+            } else {
+                *version_number += 1;
+                ["FL", "AL", "RL"]
+                    .iter()
+                    .map(|list| format!("ADD 0 {list} {version_number} {sender} {sender}\r\n"))
+                    .collect()
+            };
+
+            for reply in replies {
+                wr.write_all(reply.as_bytes()).await?;
+                trace!("S: {reply}");
+            }
+        }
+
         "GetUserDetails" => {
             trace!("Thread {sender}: {command}");
             if verify_contact::verify_contact(authenticated_user, &sender).is_ok() {
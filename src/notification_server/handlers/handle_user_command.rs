@@ -1,7 +1,15 @@
+use crate::config::Config;
+use crate::email_invitations::EmailInviter;
 use crate::errors::command_error::CommandError;
 use crate::errors::server_error::ServerError;
+use crate::limits::user_limiter::UserLimiter;
+use crate::mail::mailer::Mailer;
+use crate::mobile_pager::MobilePager;
 use crate::notification_server::commands::add::Add;
+use crate::notification_server::commands::fnd::Fnd;
+use crate::notification_server::commands::pag::Pag;
 use crate::notification_server::commands::rea::Rea;
+use crate::notification_server::commands::snd::Snd;
 use crate::notification_server::handlers::process_command::{
     process_command, process_user_command,
 };
@@ -16,13 +24,19 @@ use crate::{
 use log::{trace, warn};
 use sqlx::{MySql, Pool};
 use std::error;
+use std::sync::Arc;
 use tokio::{io::AsyncWriteExt, net::tcp::WriteHalf, sync::broadcast};
 
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_user_command(
     protocol_version: u32,
     authenticated_user: &mut AuthenticatedUser,
     pool: &Pool<MySql>,
     broadcast_tx: &broadcast::Sender<Message>,
+    config: &Arc<Config>,
+    mobile_pager: &MobilePager,
+    search_limiter: &UserLimiter,
+    mailer: &Mailer,
     wr: &mut WriteHalf<'_>,
     version_number: &mut u32,
     command: Vec<u8>,
@@ -58,7 +72,8 @@ pub async fn handle_user_command(
         }
 
         "URL" => {
-            process_command(protocol_version, wr, &Url, command).await?;
+            let url = Url::new(config.clone());
+            process_command(protocol_version, wr, &url, command).await?;
         }
 
         "CHG" => {
@@ -89,7 +104,7 @@ pub async fn handle_user_command(
         }
 
         "PRP" => {
-            let prp = Prp::new(pool.clone());
+            let prp = Prp::new(pool.clone(), broadcast_tx.clone());
             process_user_command(
                 protocol_version,
                 wr,
@@ -101,6 +116,46 @@ pub async fn handle_user_command(
             .await?;
         }
 
+        "FND" => {
+            let fnd = Fnd::new(pool.clone(), search_limiter.clone());
+            process_user_command(
+                protocol_version,
+                wr,
+                authenticated_user,
+                version_number,
+                &fnd,
+                command,
+            )
+            .await?;
+        }
+
+        "SND" => {
+            let email_inviter = EmailInviter::new(pool.clone(), config.clone(), mailer.clone());
+            let snd = Snd::new(email_inviter);
+            process_user_command(
+                protocol_version,
+                wr,
+                authenticated_user,
+                version_number,
+                &snd,
+                command,
+            )
+            .await?;
+        }
+
+        "PAG" => {
+            let pag = Pag::new(mobile_pager.clone());
+            process_user_command(
+                protocol_version,
+                wr,
+                authenticated_user,
+                version_number,
+                &pag,
+                command,
+            )
+            .await?;
+        }
+
         "SBP" => {
             let sbp = Sbp::new(pool.clone());
             process_user_command(
@@ -119,7 +174,8 @@ pub async fn handle_user_command(
         }
 
         "ADC" => {
-            let adc = Adc::new(pool.clone(), broadcast_tx.clone());
+            let email_inviter = EmailInviter::new(pool.clone(), config.clone(), mailer.clone());
+            let adc = Adc::new(pool.clone(), broadcast_tx.clone(), Some(email_inviter));
             process_user_command(
                 protocol_version,
                 wr,
@@ -132,7 +188,8 @@ pub async fn handle_user_command(
         }
 
         "ADD" => {
-            let add = Add::new(pool.clone(), broadcast_tx.clone());
+            let email_inviter = EmailInviter::new(pool.clone(), config.clone(), mailer.clone());
+            let add = Add::new(pool.clone(), broadcast_tx.clone(), Some(email_inviter));
             process_user_command(
                 protocol_version,
                 wr,
@@ -197,7 +254,7 @@ pub async fn handle_user_command(
         }
 
         "REA" => {
-            let rea = Rea::new(pool.clone());
+            let rea = Rea::new(pool.clone(), broadcast_tx.clone());
             process_user_command(
                 protocol_version,
                 wr,
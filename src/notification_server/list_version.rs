@@ -0,0 +1,42 @@
+use chrono::{NaiveDateTime, Utc};
+use sqlx::{MySql, Pool};
+
+/// Bumps the version of a user's contact list and returns the new one
+pub async fn bump(pool: &Pool<MySql>, user_id: i32) -> Result<u32, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    sqlx::query!(
+        "UPDATE users SET list_version = list_version + 1, list_updated_at = ? WHERE id = ?",
+        Utc::now().naive_utc(),
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    let user = sqlx::query!("SELECT list_version FROM users WHERE id = ?", user_id)
+        .fetch_one(&mut *transaction)
+        .await?;
+
+    transaction.commit().await?;
+    Ok(user.list_version)
+}
+
+/// Bumps the lists of everyone who has the user as a contact
+pub async fn bump_reverse_list(pool: &Pool<MySql>, user_id: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE users SET list_version = list_version + 1, list_updated_at = ?
+        WHERE id IN (SELECT user_id FROM contacts WHERE contact_id = ?)",
+        Utc::now().naive_utc(),
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Formats the time of the last change the way MSNP10+ clients send it back in SYN
+pub fn timestamp(list_updated_at: NaiveDateTime) -> String {
+    list_updated_at
+        .format("%Y-%m-%dT%H:%M:%S%.6f0-00:00")
+        .to_string()
+}
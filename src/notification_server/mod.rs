@@ -1,5 +1,8 @@
-mod commands;
+pub mod commands;
+mod display_name;
 mod handlers;
+mod list_version;
 #[allow(clippy::module_inception)]
 pub mod notification_server;
-mod verify_contact;
+mod pending_additions;
+pub mod verify_contact;
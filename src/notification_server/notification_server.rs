@@ -1,5 +1,10 @@
+use crate::config::Config;
 use crate::errors::server_error::ServerError;
 use crate::errors::thread_command_error::ThreadCommandError;
+use crate::limits::token_bucket::TokenBucket;
+use crate::limits::user_limiter::UserLimiter;
+use crate::mail::mailer::Mailer;
+use crate::mobile_pager::MobilePager;
 use crate::notification_server::commands::fln;
 use crate::notification_server::handlers::handle_authentication_command::handle_authentication_command;
 use crate::notification_server::handlers::handle_thread_command::handle_thread_command;
@@ -7,11 +12,17 @@ use crate::notification_server::handlers::handle_user_command::handle_user_comma
 use crate::notification_server::handlers::handle_ver::handle_ver;
 use crate::receive_split::receive_split;
 use crate::{Message, models::transient::authenticated_user::AuthenticatedUser};
+use log::{info, warn};
 use sqlx::{MySql, Pool};
 use std::error;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::{
+    io::AsyncWriteExt,
     net::{TcpStream, tcp::WriteHalf},
     sync::broadcast,
+    time::{Instant, sleep_until, timeout_at},
 };
 
 pub struct NotificationServer {
@@ -21,10 +32,31 @@ pub struct NotificationServer {
     authenticated_user: Option<AuthenticatedUser>,
     protocol_version: Option<u32>,
     version_number: u32,
+    config: Arc<Config>,
+    mobile_pager: MobilePager,
+    search_limiter: UserLimiter,
+    mailer: Mailer,
+    peer_address: SocketAddr,
+    connected_at: Instant,
+    last_activity: Instant,
+    rate_limiter: TokenBucket,
 }
 
 impl NotificationServer {
-    pub fn new(pool: Pool<MySql>, broadcast_tx: broadcast::Sender<Message>) -> Self {
+    pub fn new(
+        pool: Pool<MySql>,
+        broadcast_tx: broadcast::Sender<Message>,
+        config: Arc<Config>,
+        mobile_pager: MobilePager,
+        search_limiter: UserLimiter,
+        mailer: Mailer,
+        peer_address: SocketAddr,
+    ) -> Self {
+        let rate_limiter = TokenBucket::new(
+            config.limits.command_burst,
+            config.limits.commands_per_second,
+        );
+
         NotificationServer {
             pool,
             broadcast_tx: broadcast_tx.clone(),
@@ -32,6 +64,14 @@ impl NotificationServer {
             authenticated_user: None,
             protocol_version: None,
             version_number: 0,
+            config,
+            mobile_pager,
+            search_limiter,
+            mailer,
+            peer_address,
+            connected_at: Instant::now(),
+            last_activity: Instant::now(),
+            rate_limiter,
         }
     }
 
@@ -64,11 +104,40 @@ impl NotificationServer {
                 }
 
                 received = self.contact_rx.as_mut().ok_or(ThreadCommandError::ReceivingError)?.recv() => {
-                    self.handle_thread_commands(&mut wr, received?).await?;
+                    if let Err(error) = self.handle_thread_commands(&mut wr, received?).await {
+                        if let Some(ThreadCommandError::TokenRevoked
+                            | ThreadCommandError::AccountDeleted
+                            | ThreadCommandError::PasswordReset) =
+                            error.downcast_ref::<ThreadCommandError>()
+                            && let Some(user) = self.authenticated_user.as_ref()
+                        {
+                            self.broadcast_tx.send(Message::RemoveTx(user.email.clone()))?;
+                            self.send_fln_to_contacts().await?;
+                            self.broadcast_tx.send(Message::RemoveUser)?;
+                        }
+
+                        return Err(error);
+                    }
+                }
+
+                _ = sleep_until(self.last_activity + Duration::from_secs(self.config.limits.idle_timeout_seconds)) => {
+                    if let Some(user) = self.authenticated_user.as_ref() {
+                        self.broadcast_tx.send(Message::RemoveTx(user.email.clone()))?;
+                        self.send_fln_to_contacts().await?;
+                    }
+
+                    self.broadcast_tx.send(Message::RemoveUser)?;
+                    return Err(ServerError::IdleTimeout.into());
                 }
             }
         } else {
-            let messages = receive_split(&mut rd).await?;
+            let deadline = self.connected_at
+                + Duration::from_secs(self.config.limits.authentication_timeout_seconds);
+
+            let messages = timeout_at(deadline, receive_split(&mut rd))
+                .await
+                .or(Err(ServerError::AuthenticationTimeout))??;
+
             self.handle_client_commands(&mut wr, messages).await?;
         }
 
@@ -80,7 +149,14 @@ impl NotificationServer {
         wr: &mut WriteHalf<'_>,
         messages: Vec<Vec<u8>>,
     ) -> Result<(), Box<dyn error::Error + Send + Sync>> {
+        self.last_activity = Instant::now();
         for message in messages {
+            if !self.rate_limiter.try_take() {
+                wr.write_all(b"OUT\r\n").await?;
+                warn!("S: OUT\r\n");
+                return Err(ServerError::RateLimited.into());
+            }
+
             if self.protocol_version.is_none() {
                 self.protocol_version = Some(handle_ver(wr, message).await?);
                 continue;
@@ -92,6 +168,8 @@ impl NotificationServer {
                         .ok_or(ServerError::CouldNotGetProtocolVersion)?,
                     &self.pool,
                     &self.broadcast_tx,
+                    &self.config,
+                    &self.peer_address,
                     wr,
                     message,
                 )
@@ -100,6 +178,11 @@ impl NotificationServer {
                     continue;
                 };
 
+                info!(
+                    "{} logged in from {}",
+                    authenticated_user.email, self.peer_address
+                );
+
                 self.authenticated_user = Some(authenticated_user);
                 self.contact_rx = Some(contact_rx);
                 continue;
@@ -113,6 +196,10 @@ impl NotificationServer {
                     .ok_or(ServerError::CouldNotGetAuthenticatedUser)?,
                 &self.pool,
                 &self.broadcast_tx,
+                &self.config,
+                &self.mobile_pager,
+                &self.search_limiter,
+                &self.mailer,
                 wr,
                 &mut self.version_number,
                 message,
@@ -0,0 +1,114 @@
+use chrono::Utc;
+use sqlx::{Executor, MySql, Pool};
+
+/// Remembers that `added_by` put the user on their forward list, unless the user already allowed or blocked them
+pub async fn add(
+    executor: impl Executor<'_, Database = MySql>,
+    user_id: i32,
+    added_by: i32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT IGNORE INTO pending_additions (user_id, added_by, added_at)
+        SELECT ?, ?, ? FROM DUAL WHERE NOT EXISTS (
+            SELECT id FROM contacts WHERE user_id = ? AND contact_id = ?
+            AND (in_allow_list = TRUE OR in_block_list = TRUE)
+        )",
+        user_id,
+        added_by,
+        Utc::now().naive_utc(),
+        user_id,
+        added_by
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Forgets the addition once the user allowed or blocked `added_by`, or `added_by` removed them again
+pub async fn resolve(
+    executor: impl Executor<'_, Database = MySql>,
+    user_id: i32,
+    added_by: i32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "DELETE FROM pending_additions WHERE user_id = ? AND added_by = ?",
+        user_id,
+        added_by
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// The ADD RL (MSNP8-9) or ADC RL (MSNP10+) lines for additions the user hasn't answered yet
+pub async fn convert(
+    pool: &Pool<MySql>,
+    protocol_version: u32,
+    version_number: u32,
+    user_id: i32,
+) -> Result<Vec<String>, sqlx::Error> {
+    let additions = sqlx::query!(
+        "SELECT users.email, users.display_name FROM pending_additions
+        INNER JOIN users ON pending_additions.added_by = users.id
+        WHERE pending_additions.user_id = ? ORDER BY pending_additions.added_at",
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(additions
+        .into_iter()
+        .map(|addition| {
+            line(
+                protocol_version,
+                version_number,
+                &addition.email,
+                &addition.display_name,
+            )
+        })
+        .collect())
+}
+
+fn line(protocol_version: u32, version_number: u32, email: &str, display_name: &str) -> String {
+    if protocol_version >= 10 {
+        format!("ADC 0 RL N={email} F={display_name}\r\n")
+    } else {
+        format!("ADD 0 RL {version_number} {email} {display_name}\r\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::transient::authenticated_user::AuthenticatedUser;
+    use crate::notification_server::commands::{adc, add};
+    use std::sync::Arc;
+
+    #[test]
+    fn additions_are_replayed_with_the_list_version_before_msnp10() {
+        assert_eq!(
+            line(9, 42, "bob@example.com", "Bob"),
+            "ADD 0 RL 42 bob@example.com Bob\r\n"
+        );
+    }
+
+    #[test]
+    fn additions_are_replayed_without_a_list_version_from_msnp10() {
+        assert_eq!(
+            line(10, 42, "bob@example.com", "Bob"),
+            "ADC 0 RL N=bob@example.com F=Bob\r\n"
+        );
+    }
+
+    #[test]
+    fn replayed_additions_match_the_live_notifications() {
+        let mut user = AuthenticatedUser::new(Arc::new(String::from("bob@example.com")));
+        user.display_name = Arc::new(String::from("Bob"));
+
+        // The live ADD carries a placeholder version the receiving session replaces
+        assert_eq!(line(9, 0, "bob@example.com", "Bob"), add::convert(&user));
+        assert_eq!(line(12, 0, "bob@example.com", "Bob"), adc::convert(&user));
+    }
+}
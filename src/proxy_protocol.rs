@@ -0,0 +1,280 @@
+use crate::errors::proxy_protocol_error::ProxyProtocolError;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+const HEADER_TIMEOUT: Duration = Duration::from_secs(5);
+const V1_MAX_LENGTH: usize = 107;
+// Addresses plus room for the TLVs load balancers add
+const V2_MAX_LENGTH: usize = 1024;
+const V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+
+/// Returns the client address, taken from the PROXY protocol header when the listener expects one
+pub async fn peer_address(
+    socket: &mut TcpStream,
+    accepted_address: SocketAddr,
+    enabled: bool,
+) -> Result<SocketAddr, ProxyProtocolError> {
+    if !enabled {
+        return Ok(accepted_address);
+    }
+
+    let address = timeout(HEADER_TIMEOUT, read_header(socket))
+        .await
+        .or(Err(ProxyProtocolError::Timeout))??;
+
+    Ok(address.unwrap_or(accepted_address))
+}
+
+/// Reads exactly the header, so the client's first command is left in the socket
+async fn read_header(
+    socket: &mut (impl AsyncRead + Unpin),
+) -> Result<Option<SocketAddr>, ProxyProtocolError> {
+    let mut header = vec![0; 6];
+    socket.read_exact(&mut header).await?;
+
+    if header == b"PROXY " {
+        while !header.ends_with(b"\r\n") {
+            if header.len() >= V1_MAX_LENGTH {
+                return Err(ProxyProtocolError::InvalidHeader);
+            }
+
+            header.push(socket.read_u8().await?);
+        }
+
+        return parse_v1(&header);
+    }
+
+    if header != V2_SIGNATURE[..6] {
+        return Err(ProxyProtocolError::MissingHeader);
+    }
+
+    header.resize(16, 0);
+    socket.read_exact(&mut header[6..]).await?;
+
+    if header[..12] != V2_SIGNATURE || header[12] >> 4 != 2 {
+        return Err(ProxyProtocolError::InvalidHeader);
+    }
+
+    let length = u16::from_be_bytes([header[14], header[15]]) as usize;
+    if length > V2_MAX_LENGTH {
+        return Err(ProxyProtocolError::InvalidHeader);
+    }
+
+    let mut addresses = vec![0; length];
+    socket.read_exact(&mut addresses).await?;
+
+    // LOCAL connections, such as health checks, keep the proxy's address
+    if header[12] & 0x0f == 0 {
+        return Ok(None);
+    }
+
+    parse_v2(header[13], &addresses)
+}
+
+fn parse_v1(header: &[u8]) -> Result<Option<SocketAddr>, ProxyProtocolError> {
+    let header = str::from_utf8(header).or(Err(ProxyProtocolError::InvalidHeader))?;
+    let args: Vec<&str> = header.trim().split(' ').collect();
+
+    match *args.get(1).unwrap_or(&"") {
+        "TCP4" | "TCP6" if args.len() == 6 => {
+            let ip = args[2]
+                .parse::<IpAddr>()
+                .or(Err(ProxyProtocolError::InvalidHeader))?;
+
+            let port = args[4]
+                .parse::<u16>()
+                .or(Err(ProxyProtocolError::InvalidHeader))?;
+
+            Ok(Some(SocketAddr::new(ip, port)))
+        }
+
+        "UNKNOWN" => Ok(None),
+        _ => Err(ProxyProtocolError::InvalidHeader),
+    }
+}
+
+fn parse_v2(family: u8, addresses: &[u8]) -> Result<Option<SocketAddr>, ProxyProtocolError> {
+    match family >> 4 {
+        // AF_INET
+        1 => {
+            let addresses = addresses
+                .get(..12)
+                .ok_or(ProxyProtocolError::InvalidHeader)?;
+
+            let ip: [u8; 4] = addresses[..4].try_into().unwrap_or_default();
+            let port = u16::from_be_bytes([addresses[8], addresses[9]]);
+            Ok(Some(SocketAddr::new(Ipv4Addr::from(ip).into(), port)))
+        }
+
+        // AF_INET6
+        2 => {
+            let addresses = addresses
+                .get(..36)
+                .ok_or(ProxyProtocolError::InvalidHeader)?;
+
+            let ip: [u8; 16] = addresses[..16].try_into().unwrap_or_default();
+            let port = u16::from_be_bytes([addresses[32], addresses[33]]);
+            Ok(Some(SocketAddr::new(Ipv6Addr::from(ip).into(), port)))
+        }
+
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v2_header(command: u8, family: u8, addresses: &[u8]) -> Vec<u8> {
+        let mut header = V2_SIGNATURE.to_vec();
+        header.extend([0x20 | command, family]);
+        header.extend((addresses.len() as u16).to_be_bytes());
+        header.extend(addresses);
+        header
+    }
+
+    #[tokio::test]
+    async fn v1_tcp4_header_gives_the_client_address() {
+        let mut stream: &[u8] = b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 1863\r\nVER 1";
+        let address = read_header(&mut stream).await.unwrap();
+
+        assert_eq!(address, Some("192.0.2.1:56324".parse().unwrap()));
+        assert_eq!(stream, b"VER 1");
+    }
+
+    #[tokio::test]
+    async fn v1_tcp6_header_gives_the_client_address() {
+        let mut stream: &[u8] = b"PROXY TCP6 2001:db8::1 2001:db8::2 56324 1863\r\n";
+        let address = read_header(&mut stream).await.unwrap();
+
+        assert_eq!(address, Some("[2001:db8::1]:56324".parse().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn v1_unknown_header_keeps_the_proxy_address() {
+        let mut stream: &[u8] = b"PROXY UNKNOWN\r\n";
+        assert_eq!(read_header(&mut stream).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn v1_header_with_bad_addresses_is_refused() {
+        let mut stream: &[u8] = b"PROXY TCP4 192.0.2.1 198.51.100.1 port 1863\r\n";
+        assert!(matches!(
+            read_header(&mut stream).await,
+            Err(ProxyProtocolError::InvalidHeader)
+        ));
+    }
+
+    #[tokio::test]
+    async fn v1_header_without_line_end_is_refused() {
+        let mut stream: &[u8] = &[b"PROXY TCP4 ".as_slice(), &[b'1'; V1_MAX_LENGTH]].concat();
+        assert!(matches!(
+            read_header(&mut stream).await,
+            Err(ProxyProtocolError::InvalidHeader)
+        ));
+    }
+
+    #[tokio::test]
+    async fn v2_proxy_header_gives_the_client_address() {
+        let addresses = [192, 0, 2, 1, 198, 51, 100, 1, 0xdc, 0x04, 0x07, 0x47];
+        let mut stream = &[v2_header(1, 0x11, &addresses), b"VER 1".to_vec()].concat()[..];
+        let address = read_header(&mut stream).await.unwrap();
+
+        assert_eq!(address, Some("192.0.2.1:56324".parse().unwrap()));
+        assert_eq!(stream, b"VER 1");
+    }
+
+    #[tokio::test]
+    async fn v2_proxy_header_gives_the_ipv6_client_address() {
+        let mut addresses = [0; 36];
+        addresses[..16].copy_from_slice(&"2001:db8::1".parse::<Ipv6Addr>().unwrap().octets());
+        addresses[32..34].copy_from_slice(&56324u16.to_be_bytes());
+
+        let mut stream = &v2_header(1, 0x21, &addresses)[..];
+        let address = read_header(&mut stream).await.unwrap();
+
+        assert_eq!(address, Some("[2001:db8::1]:56324".parse().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn v2_local_header_keeps_the_proxy_address() {
+        let mut stream = &[v2_header(0, 0x00, &[]), b"VER 1".to_vec()].concat()[..];
+
+        assert_eq!(read_header(&mut stream).await.unwrap(), None);
+        assert_eq!(stream, b"VER 1");
+    }
+
+    #[tokio::test]
+    async fn v2_header_shorter_than_its_addresses_is_refused() {
+        let mut stream = &v2_header(1, 0x11, &[192, 0, 2, 1])[..];
+        assert!(matches!(
+            read_header(&mut stream).await,
+            Err(ProxyProtocolError::InvalidHeader)
+        ));
+    }
+
+    #[tokio::test]
+    async fn oversized_v2_length_is_refused() {
+        let mut stream = &v2_header(1, 0x11, &[0; V2_MAX_LENGTH + 1])[..];
+        assert!(matches!(
+            read_header(&mut stream).await,
+            Err(ProxyProtocolError::InvalidHeader)
+        ));
+    }
+
+    #[tokio::test]
+    async fn v2_length_past_the_end_of_the_stream_is_refused() {
+        let mut header = v2_header(1, 0x11, &[0; 12]);
+        header[14..16].copy_from_slice(&(V2_MAX_LENGTH as u16).to_be_bytes());
+
+        let mut stream = &header[..];
+        assert!(matches!(
+            read_header(&mut stream).await,
+            Err(ProxyProtocolError::Io(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn truncated_headers_are_refused() {
+        for header in [&b"PROX"[..], b"PROXY TCP4 192.0.2.1", &V2_SIGNATURE[..10]] {
+            let mut stream = header;
+            assert!(matches!(
+                read_header(&mut stream).await,
+                Err(ProxyProtocolError::Io(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn bad_signatures_are_refused() {
+        let mut stream: &[u8] = b"VER 1 MSNP8 CVR0\r\n";
+        assert!(matches!(
+            read_header(&mut stream).await,
+            Err(ProxyProtocolError::MissingHeader)
+        ));
+
+        let mut header = v2_header(1, 0x11, &[0; 12]);
+        header[11] = b'X';
+
+        let mut stream = &header[..];
+        assert!(matches!(
+            read_header(&mut stream).await,
+            Err(ProxyProtocolError::InvalidHeader)
+        ));
+    }
+
+    #[tokio::test]
+    async fn unsupported_v2_version_is_refused() {
+        let mut header = v2_header(1, 0x11, &[0; 12]);
+        header[12] = 0x11;
+
+        let mut stream = &header[..];
+        assert!(matches!(
+            read_header(&mut stream).await,
+            Err(ProxyProtocolError::InvalidHeader)
+        ));
+    }
+}
@@ -24,7 +24,7 @@ pub async fn receive_split(rd: &mut ReadHalf<'_>) -> Result<Vec<Vec<u8>>, Receiv
         let args: Vec<&str> = command.trim().split(' ').collect();
 
         match *args.first().unwrap_or(&"") {
-            "UUX" | "MSG" => {
+            "UUX" | "MSG" | "PAG" => {
                 let length_index = match *args.first().unwrap_or(&"") {
                     "UUX" => 2,
                     _ => 3,
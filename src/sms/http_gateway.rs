@@ -0,0 +1,56 @@
+use super::{sms::Sms, sms_gateway::SmsGateway};
+use crate::config::SmsHttpConfig;
+use crate::errors::sms_error::SmsError;
+use serde::Serialize;
+
+/// Posts messages as JSON to an HTTP endpoint, such as a provider's API or a small bridge in front of one
+#[derive(Debug, Clone)]
+pub struct HttpGateway {
+    client: reqwest::Client,
+    url: String,
+    token: String,
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    from: &'a str,
+    to: &'a str,
+    body: &'a str,
+}
+
+impl HttpGateway {
+    pub fn new(config: &SmsHttpConfig) -> Result<Self, SmsError> {
+        if config.url.is_empty() {
+            return Err(SmsError::MissingUrl);
+        }
+
+        Ok(HttpGateway {
+            client: reqwest::Client::builder().build()?,
+            url: config.url.clone(),
+            token: config.token.clone(),
+        })
+    }
+}
+
+impl SmsGateway for HttpGateway {
+    async fn send(&self, sms: &Sms) -> Result<(), SmsError> {
+        let request = self.client.post(&self.url).json(&Request {
+            from: &sms.from,
+            to: &sms.to,
+            body: &sms.body,
+        });
+
+        let request = if self.token.is_empty() {
+            request
+        } else {
+            request.bearer_auth(&self.token)
+        };
+
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(SmsError::Rejected(response.status()));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,6 @@
+pub mod http_gateway;
+pub mod pager;
+#[allow(clippy::module_inception)]
+pub mod sms;
+pub mod sms_gateway;
+pub mod spool_gateway;
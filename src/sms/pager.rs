@@ -0,0 +1,30 @@
+use super::{
+    http_gateway::HttpGateway, sms::Sms, sms_gateway::SmsGateway, spool_gateway::SpoolGateway,
+};
+use crate::config::{SmsConfig, SmsGatewayKind};
+use crate::errors::sms_error::SmsError;
+
+/// The SMS gateway selected in the configuration
+#[derive(Clone)]
+pub enum Pager {
+    Spool(SpoolGateway),
+    Http(HttpGateway),
+}
+
+impl Pager {
+    pub fn new(config: &SmsConfig) -> Result<Self, SmsError> {
+        Ok(match config.gateway {
+            SmsGatewayKind::Spool => Pager::Spool(SpoolGateway::new(config.directory.clone())),
+            SmsGatewayKind::Http => Pager::Http(HttpGateway::new(&config.http)?),
+        })
+    }
+}
+
+impl SmsGateway for Pager {
+    async fn send(&self, sms: &Sms) -> Result<(), SmsError> {
+        match self {
+            Pager::Spool(gateway) => gateway.send(sms).await,
+            Pager::Http(gateway) => gateway.send(sms).await,
+        }
+    }
+}
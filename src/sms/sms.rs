@@ -0,0 +1,6 @@
+#[derive(Debug, Clone)]
+pub struct Sms {
+    pub from: String,
+    pub to: String,
+    pub body: String,
+}
@@ -0,0 +1,6 @@
+use super::sms::Sms;
+use crate::errors::sms_error::SmsError;
+
+pub trait SmsGateway {
+    fn send(&self, sms: &Sms) -> impl Future<Output = Result<(), SmsError>> + Send;
+}
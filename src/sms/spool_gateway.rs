@@ -0,0 +1,42 @@
+use super::{sms::Sms, sms_gateway::SmsGateway};
+use crate::errors::sms_error::SmsError;
+use chrono::Utc;
+use log::info;
+use rand::distr::SampleString;
+use rand_distr::Alphanumeric;
+use std::path::PathBuf;
+
+/// Writes messages to a spool directory, for running without an SMS provider or feeding one from another process
+#[derive(Debug, Clone)]
+pub struct SpoolGateway {
+    directory: PathBuf,
+}
+
+impl SpoolGateway {
+    pub fn new(directory: PathBuf) -> Self {
+        SpoolGateway { directory }
+    }
+}
+
+impl SmsGateway for SpoolGateway {
+    async fn send(&self, sms: &Sms) -> Result<(), SmsError> {
+        let contents = format!(
+            "From: {}\r\nTo: {}\r\n\r\n{}\r\n",
+            sms.from, sms.to, sms.body
+        );
+
+        tokio::fs::create_dir_all(&self.directory).await?;
+        // The random part keeps messages written in the same instant from replacing each other
+        let path = self.directory.join(format!(
+            "{}-{}-{}.sms",
+            Utc::now().format("%Y%m%d%H%M%S%f"),
+            Alphanumeric.sample_string(&mut rand::rng(), 8),
+            sms.to
+                .replace(|c: char| !c.is_ascii_alphanumeric() && c != '+', "_")
+        ));
+
+        tokio::fs::write(&path, contents).await?;
+        info!("Wrote SMS to {}", path.display());
+        Ok(())
+    }
+}
@@ -1,5 +1,7 @@
 use super::{joi, traits::authentication_command::AuthenticationCommand};
+use crate::config::Config;
 use crate::errors::command_error::CommandError;
+use crate::limits::session_limiter::{SessionGuard, SessionLimiter};
 use crate::{
     message::Message,
     models::transient::{authenticated_user::AuthenticatedUser, principal::Principal},
@@ -9,14 +11,26 @@ use core::str;
 use std::sync::Arc;
 use tokio::sync::broadcast::{self, error::RecvError};
 
-pub struct Ans;
+pub struct Ans {
+    config: Arc<Config>,
+    session_limiter: SessionLimiter,
+}
+
+impl Ans {
+    pub fn new(config: Arc<Config>, session_limiter: SessionLimiter) -> Self {
+        Ans {
+            config,
+            session_limiter,
+        }
+    }
+}
 
 impl AuthenticationCommand for Ans {
     async fn handle(
         &self,
         broadcast_tx: &broadcast::Sender<Message>,
         command: &[u8],
-    ) -> Result<(Vec<String>, u32, Session, AuthenticatedUser), CommandError> {
+    ) -> Result<(Vec<String>, u32, Session, AuthenticatedUser, SessionGuard), CommandError> {
         let command_string = unsafe { str::from_utf8_unchecked(command) };
         let args: Vec<&str> = command_string.trim().split(' ').collect();
 
@@ -30,8 +44,9 @@ impl AuthenticationCommand for Ans {
             .get(3)
             .ok_or(CommandError::Reply(format!("201 {tr_id}\r\n")))?;
 
+        let ticket = Arc::new(cki_string.to_string());
         broadcast_tx
-            .send(Message::GetSession(Arc::new(cki_string.to_string())))
+            .send(Message::GetSession(ticket.clone()))
             .map_err(CommandError::CouldNotSendToBroadcast)?;
 
         let mut session;
@@ -66,6 +81,11 @@ impl AuthenticationCommand for Ans {
             return Err(CommandError::ReplyAndDisconnect(format!("911 {tr_id}\r\n")));
         };
 
+        // The ticket is only used up once the user is let in, so it can be retried after a 713 or 714
+        if !session.accepts_ticket(&ticket, &user_email) {
+            return Err(CommandError::ReplyAndDisconnect(format!("911 {tr_id}\r\n")));
+        }
+
         let message = Message::ToContact {
             sender: user_email.clone(),
             receiver: user_email.clone(),
@@ -119,6 +139,11 @@ impl AuthenticationCommand for Ans {
         let protocol_version =
             protocol_version_result.ok_or(CommandError::CouldNotGetProtocolVersion)?;
 
+        let session_guard = self
+            .session_limiter
+            .acquire(Arc::new(authenticated_user.email.to_lowercase()))
+            .ok_or(CommandError::ReplyAndDisconnect(format!("714 {tr_id}\r\n")))?;
+
         let mut replies = Vec::new();
         {
             let mut principals =
@@ -129,10 +154,17 @@ impl AuthenticationCommand for Ans {
                         "500 {tr_id}\r\n"
                     ))))?;
 
-            let count = principals.len();
-            let mut index = 1;
+            let max_principals = self.config.switchboard.max_principals;
+            if max_principals != 0 && principals.len() >= max_principals {
+                return Err(CommandError::ReplyAndDisconnect(format!("713 {tr_id}\r\n")));
+            }
+
+            if !session.redeem_ticket(&ticket, &user_email) {
+                return Err(CommandError::ReplyAndDisconnect(format!("911 {tr_id}\r\n")));
+            }
 
-            for principal in principals.values() {
+            let count = principals.len();
+            for (index, principal) in (1..).zip(principals.values()) {
                 let email = &principal.email;
                 let display_name = &principal.display_name;
 
@@ -145,7 +177,6 @@ impl AuthenticationCommand for Ans {
                 };
 
                 replies.push(iro_reply);
-                index += 1;
             }
 
             principals.insert(
@@ -154,10 +185,16 @@ impl AuthenticationCommand for Ans {
                     email: user_email.clone(),
                     display_name: authenticated_user.display_name.clone(),
                     client_id: authenticated_user.client_id,
+                    blocked: authenticated_user.block_list(),
+                    mobile: false,
                 },
             );
         }
 
+        broadcast_tx
+            .send(Message::RemoveTicket(ticket))
+            .map_err(CommandError::CouldNotSendToBroadcast)?;
+
         let joi = joi::generate(protocol_version, &mut authenticated_user, tr_id);
         let message = Message::ToPrincipals {
             sender: user_email.clone(),
@@ -172,6 +209,12 @@ impl AuthenticationCommand for Ans {
             ))))?;
 
         replies.push(format!("ANS {tr_id} OK\r\n"));
-        Ok((replies, protocol_version, session, authenticated_user))
+        Ok((
+            replies,
+            protocol_version,
+            session,
+            authenticated_user,
+            session_guard,
+        ))
     }
 }
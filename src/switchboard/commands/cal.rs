@@ -1,22 +1,108 @@
 use super::rng;
+use crate::config::Config;
 use crate::errors::command_error::CommandError;
+use crate::errors::contact_verification_error::ContactVerificationError;
 use crate::errors::invitation_error::InvitationError;
+use crate::models::transient::principal::Principal;
+use crate::notification_server::verify_contact;
 use crate::switchboard::commands::traits::command::Command;
 use crate::{
     message::Message, models::transient::authenticated_user::AuthenticatedUser,
     switchboard::session::Session,
 };
 use core::str;
+use std::collections::HashSet;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::time::timeout;
+
+const MOBILE_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub struct Cal {
     broadcast_tx: broadcast::Sender<Message>,
+    config: Arc<Config>,
+}
+
+impl Cal {
+    pub fn new(broadcast_tx: broadcast::Sender<Message>, config: Arc<Config>) -> Self {
+        Cal {
+            broadcast_tx,
+            config,
+        }
+    }
 }
 
 impl Cal {
-    pub fn new(broadcast_tx: broadcast::Sender<Message>) -> Self {
-        Cal { broadcast_tx }
+    /// Offline contacts who can be paged join as mobile principals, and the messages sent in the session are paged to them
+    async fn call_mobile(
+        &self,
+        tr_id: &str,
+        user: &AuthenticatedUser,
+        session: &Session,
+        email: Arc<String>,
+    ) -> Result<Vec<String>, CommandError> {
+        let mut broadcast_rx = self.broadcast_tx.subscribe();
+        self.broadcast_tx
+            .send(Message::GetMobile {
+                sender: user.email.clone(),
+                receiver: email.clone(),
+            })
+            .map_err(CommandError::CouldNotSendToBroadcast)?;
+
+        let reachable = timeout(MOBILE_TIMEOUT, async {
+            loop {
+                match broadcast_rx.recv().await {
+                    Ok(Message::Mobile {
+                        sender,
+                        receiver,
+                        reachable,
+                    }) if sender == email && receiver == user.email => return reachable,
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(_) => return false,
+                }
+            }
+        })
+        .await
+        .unwrap_or(false);
+
+        if !reachable {
+            return Err(CommandError::Reply(format!("217 {tr_id}\r\n")));
+        }
+
+        {
+            let mut principals =
+                session
+                    .principals
+                    .lock()
+                    .or(Err(CommandError::ReplyAndDisconnect(format!(
+                        "500 {tr_id}\r\n"
+                    ))))?;
+
+            principals.insert(
+                email.clone(),
+                Principal {
+                    email: email.clone(),
+                    display_name: email.clone(),
+                    client_id: None,
+                    blocked: HashSet::new(),
+                    mobile: true,
+                },
+            );
+        }
+
+        session
+            .session_tx
+            .send(Message::ToPrincipals {
+                sender: email.clone(),
+                message: format!("JOI {email} {email}\r\n").into_bytes(),
+            })
+            .or(Err(CommandError::Reply(format!("217 {tr_id}\r\n"))))?;
+
+        Ok(vec![format!(
+            "CAL {tr_id} RINGING {}\r\n",
+            session.session_id
+        )])
     }
 }
 
@@ -51,6 +137,11 @@ impl Command for Cal {
             if principals.contains_key(&email) {
                 return Err(CommandError::Reply(format!("215 {tr_id}\r\n")));
             }
+
+            let max_principals = self.config.switchboard.max_principals;
+            if max_principals != 0 && principals.len() >= max_principals {
+                return Err(CommandError::Reply(format!("713 {tr_id}\r\n")));
+            }
         }
 
         let message = Message::ToContact {
@@ -96,6 +187,17 @@ impl Command for Cal {
             }
         }
 
+        // Being blocked looks the same to the caller as the user being offline
+        if principal_user.as_ref().is_some_and(|principal_user| {
+            matches!(
+                verify_contact::verify_contact(principal_user, &user.email),
+                Err(ContactVerificationError::ContactNotInAllowList
+                    | ContactVerificationError::ContactInBlockList)
+            )
+        }) {
+            return Err(CommandError::Reply(format!("217 {tr_id}\r\n")));
+        }
+
         if let Ok(presence) = principal_user
             .ok_or(InvitationError::PrincipalUserNotFound)
             .and_then(|authenticated_user| {
@@ -105,14 +207,33 @@ impl Command for Cal {
             })
         {
             if *presence == "HDN" {
-                return Err(CommandError::Reply(format!("217 {tr_id}\r\n")));
+                return self.call_mobile(tr_id, user, session, email).await;
             }
         } else {
-            return Err(CommandError::Reply(format!("217 {tr_id}\r\n")));
+            return self.call_mobile(tr_id, user, session, email).await;
         }
 
-        let rng = rng::generate(&session.session_id, &session.cki_string, user)
-            .map_err(CommandError::CouldNotCreateRng)?;
+        let Some(ticket) = session.issue_ticket(
+            email.clone(),
+            Duration::from_secs(self.config.switchboard.ticket_lifetime_seconds),
+        ) else {
+            return Err(CommandError::ReplyAndDisconnect(format!("500 {tr_id}\r\n")));
+        };
+
+        self.broadcast_tx
+            .send(Message::SetTicket {
+                key: ticket.clone(),
+                value: session.cki_string.clone(),
+            })
+            .map_err(CommandError::CouldNotSendToBroadcast)?;
+
+        let rng = rng::generate(
+            &session.session_id,
+            &self.config.switchboard_address(),
+            &ticket,
+            user,
+        );
+
         let message = Message::ToContact {
             sender: user.email.clone(),
             receiver: email,
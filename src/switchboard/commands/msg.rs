@@ -5,8 +5,17 @@ use crate::{
     switchboard::session::Session,
 };
 use core::str;
+use tokio::sync::broadcast;
 
-pub struct Msg;
+pub struct Msg {
+    broadcast_tx: broadcast::Sender<Message>,
+}
+
+impl Msg {
+    pub fn new(broadcast_tx: broadcast::Sender<Message>) -> Self {
+        Msg { broadcast_tx }
+    }
+}
 
 impl Command for Msg {
     async fn handle(
@@ -42,6 +51,28 @@ impl Command for Msg {
             "MSG {email} {display_name} {length}\r\n"
         )));
 
+        if let Some(text) = text(&command[command_string.len()..]) {
+            let mobile_principals: Vec<_> = session
+                .principals
+                .lock()
+                .map(|principals| {
+                    principals
+                        .values()
+                        .filter(|principal| principal.mobile)
+                        .map(|principal| principal.email.clone())
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            for receiver in mobile_principals {
+                let _ = self.broadcast_tx.send(Message::Page {
+                    sender: email.clone(),
+                    receiver,
+                    message: text.clone(),
+                });
+            }
+        }
+
         let mut command = command.to_vec();
         command.splice(..command_string.len(), async_msg);
 
@@ -62,3 +93,19 @@ impl Command for Msg {
         Ok(vec![])
     }
 }
+
+/// The body of a plain text message, typing notifications and other control messages aren't paged
+fn text(payload: &[u8]) -> Option<String> {
+    let payload = str::from_utf8(payload).ok()?;
+    let (headers, body) = payload.split_once("\r\n\r\n")?;
+    if !headers.lines().any(|header| {
+        header
+            .to_ascii_lowercase()
+            .starts_with("content-type: text/plain")
+    }) || body.trim().is_empty()
+    {
+        return None;
+    }
+
+    Some(body.to_string())
+}
@@ -1,4 +1,5 @@
 use crate::errors::command_error::CommandError;
+use crate::limits::session_limiter::SessionGuard;
 use crate::{
     message::Message, models::transient::authenticated_user::AuthenticatedUser,
     switchboard::session::Session,
@@ -10,5 +11,5 @@ pub trait AuthenticationCommand {
         &self,
         broadcast_tx: &broadcast::Sender<Message>,
         command: &[u8],
-    ) -> Result<(Vec<String>, u32, Session, AuthenticatedUser), CommandError>;
+    ) -> Result<(Vec<String>, u32, Session, AuthenticatedUser, SessionGuard), CommandError>;
 }
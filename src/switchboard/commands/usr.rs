@@ -1,5 +1,6 @@
 use super::traits::authentication_command::AuthenticationCommand;
 use crate::errors::command_error::CommandError;
+use crate::limits::session_limiter::{SessionGuard, SessionLimiter};
 use crate::{
     message::Message,
     models::transient::{authenticated_user::AuthenticatedUser, principal::Principal},
@@ -9,14 +10,22 @@ use core::str;
 use std::sync::Arc;
 use tokio::sync::broadcast::{self, error::RecvError};
 
-pub struct Usr;
+pub struct Usr {
+    session_limiter: SessionLimiter,
+}
+
+impl Usr {
+    pub fn new(session_limiter: SessionLimiter) -> Self {
+        Usr { session_limiter }
+    }
+}
 
 impl AuthenticationCommand for Usr {
     async fn handle(
         &self,
         broadcast_tx: &broadcast::Sender<Message>,
         command: &[u8],
-    ) -> Result<(Vec<String>, u32, Session, AuthenticatedUser), CommandError> {
+    ) -> Result<(Vec<String>, u32, Session, AuthenticatedUser, SessionGuard), CommandError> {
         let command_string = unsafe { str::from_utf8_unchecked(command) };
         let args: Vec<&str> = command_string.trim().split(' ').collect();
 
@@ -30,8 +39,9 @@ impl AuthenticationCommand for Usr {
             .get(3)
             .ok_or(CommandError::Reply(format!("201 {tr_id}\r\n")))?;
 
+        let ticket = Arc::new(cki_string.to_string());
         broadcast_tx
-            .send(Message::GetSession(Arc::new(cki_string.to_string())))
+            .send(Message::GetSession(ticket.clone()))
             .map_err(CommandError::CouldNotSendToBroadcast)?;
 
         let mut session;
@@ -67,6 +77,11 @@ impl AuthenticationCommand for Usr {
             return Err(CommandError::ReplyAndDisconnect(format!("911 {tr_id}\r\n")));
         };
 
+        // The ticket is only used up once the user is let in, so it can be retried after a 714
+        if !session.accepts_ticket(&ticket, &user_email) {
+            return Err(CommandError::ReplyAndDisconnect(format!("911 {tr_id}\r\n")));
+        }
+
         let message = Message::ToContact {
             sender: user_email.clone(),
             receiver: user_email.clone(),
@@ -119,6 +134,19 @@ impl AuthenticationCommand for Usr {
         let protocol_version =
             protocol_version_result.ok_or(CommandError::CouldNotGetProtocolVersion)?;
 
+        let session_guard = self
+            .session_limiter
+            .acquire(Arc::new(authenticated_user.email.to_lowercase()))
+            .ok_or(CommandError::ReplyAndDisconnect(format!("714 {tr_id}\r\n")))?;
+
+        if !session.redeem_ticket(&ticket, &user_email) {
+            return Err(CommandError::ReplyAndDisconnect(format!("911 {tr_id}\r\n")));
+        }
+
+        broadcast_tx
+            .send(Message::RemoveTicket(ticket))
+            .map_err(CommandError::CouldNotSendToBroadcast)?;
+
         let user_email = &authenticated_user.email;
         let user_display_name = &authenticated_user.display_name;
 
@@ -134,6 +162,8 @@ impl AuthenticationCommand for Usr {
                     email: user_email.clone(),
                     display_name: authenticated_user.display_name.clone(),
                     client_id: authenticated_user.client_id,
+                    blocked: authenticated_user.block_list(),
+                    mobile: false,
                 },
             );
         }
@@ -145,6 +175,7 @@ impl AuthenticationCommand for Usr {
             protocol_version,
             session,
             authenticated_user,
+            session_guard,
         ))
     }
 }
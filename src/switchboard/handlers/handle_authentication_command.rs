@@ -1,4 +1,6 @@
+use crate::config::Config;
 use crate::errors::command_error::CommandError;
+use crate::limits::session_limiter::{SessionGuard, SessionLimiter};
 use crate::switchboard::handlers::process_command::process_authentication_command;
 use crate::{
     message::Message,
@@ -11,6 +13,7 @@ use crate::{
 use core::str;
 use log::{trace, warn};
 use std::error;
+use std::sync::Arc;
 use tokio::{
     net::tcp::WriteHalf,
     sync::broadcast::{self},
@@ -18,9 +21,14 @@ use tokio::{
 
 pub async fn handle_authentication_command(
     broadcast_tx: &broadcast::Sender<Message>,
+    config: &Arc<Config>,
+    session_limiter: &SessionLimiter,
     wr: &mut WriteHalf<'_>,
     command: Vec<u8>,
-) -> Result<Option<(u32, Session, AuthenticatedUser)>, Box<dyn error::Error + Send + Sync>> {
+) -> Result<
+    Option<(u32, Session, AuthenticatedUser, SessionGuard)>,
+    Box<dyn error::Error + Send + Sync>,
+> {
     let command_string = unsafe { str::from_utf8_unchecked(&command) };
     let command_string = command_string
         .lines()
@@ -30,27 +38,21 @@ pub async fn handle_authentication_command(
         + "\r\n";
 
     let args: Vec<&str> = command_string.trim().split(' ').collect();
-    match *args.first().unwrap_or(&"") {
-        "USR" => {
-            if args.len() < 4 {
-                return Err(CommandError::NotEnoughArguments.into());
-            }
+    if !matches!(*args.first().unwrap_or(&""), "USR" | "ANS") {
+        warn!("Unmatched command before authentication: {command_string}");
+        return Ok(None);
+    }
 
-            trace!("C: {} {} {} xxxxx\r\n", args[0], args[1], args[2]);
-            return process_authentication_command(broadcast_tx, wr, &Usr, &command).await;
-        }
+    if args.len() < 4 {
+        return Err(CommandError::NotEnoughArguments.into());
+    }
 
-        "ANS" => {
-            if args.len() < 4 {
-                return Err(CommandError::NotEnoughArguments.into());
-            }
-
-            trace!("C: {} {} {} xxxxx\r\n", args[0], args[1], args[2]);
-            return process_authentication_command(broadcast_tx, wr, &Ans, &command).await;
-        }
-
-        _ => warn!("Unmatched command before authentication: {command_string}"),
-    };
-
-    Ok(None)
+    trace!("C: {} {} {} xxxxx\r\n", args[0], args[1], args[2]);
+    if args[0] == "USR" {
+        let usr = Usr::new(session_limiter.clone());
+        process_authentication_command(broadcast_tx, wr, &usr, &command).await
+    } else {
+        let ans = Ans::new(config.clone(), session_limiter.clone());
+        process_authentication_command(broadcast_tx, wr, &ans, &command).await
+    }
 }
@@ -1,3 +1,4 @@
+use crate::config::Config;
 use crate::errors::command_error::CommandError;
 use crate::errors::server_error::ServerError;
 use crate::switchboard::handlers::process_command::process_session_command;
@@ -12,6 +13,7 @@ use crate::{
 use core::str;
 use log::{trace, warn};
 use std::error;
+use std::sync::Arc;
 use tokio::{
     io::AsyncWriteExt,
     net::tcp::WriteHalf,
@@ -23,6 +25,7 @@ pub async fn handle_session_command(
     authenticated_user: &mut AuthenticatedUser,
     session: &mut Session,
     broadcast_tx: &broadcast::Sender<Message>,
+    config: &Arc<Config>,
     wr: &mut WriteHalf<'_>,
     command: Vec<u8>,
 ) -> Result<(), Box<dyn error::Error + Send + Sync>> {
@@ -55,7 +58,7 @@ pub async fn handle_session_command(
         }
 
         "CAL" => {
-            let cal = Cal::new(broadcast_tx.clone());
+            let cal = Cal::new(broadcast_tx.clone(), config.clone());
             process_session_command(
                 protocol_version,
                 authenticated_user,
@@ -68,12 +71,13 @@ pub async fn handle_session_command(
         }
 
         "MSG" => {
+            let msg = Msg::new(broadcast_tx.clone());
             process_session_command(
                 protocol_version,
                 authenticated_user,
                 session,
                 wr,
-                &Msg,
+                &msg,
                 &command,
             )
             .await?;
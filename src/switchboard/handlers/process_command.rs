@@ -1,4 +1,5 @@
 use crate::errors::command_error::CommandError;
+use crate::limits::session_limiter::SessionGuard;
 use crate::{
     message::Message,
     models::transient::authenticated_user::AuthenticatedUser,
@@ -16,15 +17,23 @@ pub async fn process_authentication_command(
     wr: &mut WriteHalf<'_>,
     command: &impl AuthenticationCommand,
     message: &[u8],
-) -> Result<Option<(u32, Session, AuthenticatedUser)>, Box<dyn error::Error + Send + Sync>> {
+) -> Result<
+    Option<(u32, Session, AuthenticatedUser, SessionGuard)>,
+    Box<dyn error::Error + Send + Sync>,
+> {
     match command.handle(broadcast_tx, message).await {
-        Ok((responses, protocol_version, session, authenticated_user)) => {
+        Ok((responses, protocol_version, session, authenticated_user, session_guard)) => {
             for reply in &responses {
                 wr.write_all(reply.as_bytes()).await?;
                 trace!("S: {reply}");
             }
 
-            Ok(Some((protocol_version, session, authenticated_user)))
+            Ok(Some((
+                protocol_version,
+                session,
+                authenticated_user,
+                session_guard,
+            )))
         }
 
         Err(CommandError::Reply(err)) => {
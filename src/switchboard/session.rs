@@ -1,12 +1,275 @@
-use crate::{message::Message, models::transient::principal::Principal};
+use crate::{
+    message::Message,
+    models::transient::{principal::Principal, ticket::Ticket},
+};
+use rand::distr::SampleString;
+use rand_distr::Alphanumeric;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::sync::broadcast;
 
+/// How often session maps are checked for abandoned sessions
+pub const SWEEP_INTERVAL: Duration = Duration::from_secs(30);
+
 #[derive(Debug, Clone)]
 pub struct Session {
     pub session_tx: broadcast::Sender<Message>,
     pub session_id: Arc<String>,
     pub cki_string: Arc<String>,
     pub principals: Arc<Mutex<HashMap<Arc<String>, Principal>>>,
+    pub tickets: Arc<Mutex<HashMap<Arc<String>, Ticket>>>,
+    pub created_at: Instant,
+}
+
+impl Session {
+    /// The CKI from XFR is the first ticket, bound to the user who asked for the session
+    pub fn new(
+        session_id: Arc<String>,
+        cki_string: Arc<String>,
+        email: Arc<String>,
+        capacity: usize,
+        ticket_lifetime: Duration,
+    ) -> Self {
+        let (session_tx, _) = broadcast::channel::<Message>(capacity);
+        let tickets = HashMap::from([(
+            cki_string.clone(),
+            Ticket {
+                email,
+                expires_at: Instant::now() + ticket_lifetime,
+            },
+        )]);
+
+        Session {
+            session_tx,
+            session_id,
+            cki_string,
+            principals: Arc::new(Mutex::new(HashMap::new())),
+            tickets: Arc::new(Mutex::new(tickets)),
+            created_at: Instant::now(),
+        }
+    }
+
+    /// Whether nobody is connected to the session and its ticket has expired, either because it was never used or everyone left
+    pub fn is_abandoned(&self, ticket_lifetime: Duration) -> bool {
+        self.created_at.elapsed() >= ticket_lifetime
+            && self
+                .principals
+                .lock()
+                .is_ok_and(|principals| principals.values().all(|principal| principal.mobile))
+    }
+
+    /// Whether the principal `email` has `sender` on their block list, which is kept up to date by `SetBlocked`
+    pub fn blocks(&self, email: &Arc<String>, sender: &Arc<String>) -> bool {
+        self.principals.lock().is_ok_and(|principals| {
+            principals
+                .get(email)
+                .is_some_and(|principal| principal.blocked.contains(sender))
+        })
+    }
+
+    pub fn has_ticket(&self, ticket: &Arc<String>) -> bool {
+        self.tickets.lock().is_ok_and(|tickets| {
+            tickets
+                .get(ticket)
+                .is_some_and(|ticket| ticket.expires_at > Instant::now())
+        })
+    }
+
+    /// Creates a one-time ticket that only lets `email` join
+    pub fn issue_ticket(&self, email: Arc<String>, lifetime: Duration) -> Option<Arc<String>> {
+        let mut tickets = self.tickets.lock().ok()?;
+        tickets.retain(|_, ticket| ticket.expires_at > Instant::now());
+
+        let ticket = Arc::new(Alphanumeric.sample_string(&mut rand::rng(), 16));
+        tickets.insert(
+            ticket.clone(),
+            Ticket {
+                email,
+                expires_at: Instant::now() + lifetime,
+            },
+        );
+
+        Some(ticket)
+    }
+
+    /// Whether the ticket was issued to `email` and hasn't expired, without using it up
+    pub fn accepts_ticket(&self, ticket: &Arc<String>, email: &str) -> bool {
+        self.tickets.lock().is_ok_and(|tickets| {
+            tickets.get(ticket).is_some_and(|ticket| {
+                ticket.expires_at > Instant::now() && ticket.email.eq_ignore_ascii_case(email)
+            })
+        })
+    }
+
+    /// Uses up the ticket if it was issued to `email` and hasn't expired
+    pub fn redeem_ticket(&self, ticket: &Arc<String>, email: &str) -> bool {
+        let Ok(mut tickets) = self.tickets.lock() else {
+            return false;
+        };
+
+        tickets.retain(|_, ticket| ticket.expires_at > Instant::now());
+        if !tickets
+            .get(ticket)
+            .is_some_and(|ticket| ticket.email.eq_ignore_ascii_case(email))
+        {
+            return false;
+        }
+
+        tickets.remove(ticket);
+        true
+    }
+}
+
+/// Looks a session up by its CKI or by a ticket issued for it, `tickets` maps tickets to CKIs
+pub fn find<'a>(
+    sessions: &'a HashMap<Arc<String>, Session>,
+    tickets: &HashMap<Arc<String>, Arc<String>>,
+    ticket: &Arc<String>,
+) -> Option<&'a Session> {
+    sessions.get(ticket).or_else(|| {
+        tickets
+            .get(ticket)
+            .and_then(|cki_string| sessions.get(cki_string))
+    })
+}
+
+/// Tells every session `email` is in to close their connections
+pub fn end_sessions(sessions: &HashMap<Arc<String>, Session>, email: &Arc<String>) {
+    for session in sessions.values() {
+        if session
+            .principals
+            .lock()
+            .is_ok_and(|principals| principals.contains_key(email))
+        {
+            let _ = session.session_tx.send(Message::EndSessions(email.clone()));
+        }
+    }
+}
+
+/// Removes a session along with the tickets that still point to it
+pub fn remove(
+    sessions: &mut HashMap<Arc<String>, Session>,
+    tickets: &mut HashMap<Arc<String>, Arc<String>>,
+    cki_string: &Arc<String>,
+) {
+    if let Some(session) = sessions.remove(cki_string)
+        && let Ok(session_tickets) = session.tickets.lock()
+    {
+        for ticket in session_tickets.keys() {
+            tickets.remove(ticket);
+        }
+    }
+}
+
+/// Drops abandoned sessions and tickets that were used up, expired or belong to a session that's gone
+pub fn sweep(
+    sessions: &mut HashMap<Arc<String>, Session>,
+    tickets: &mut HashMap<Arc<String>, Arc<String>>,
+    ticket_lifetime: Duration,
+) {
+    sessions.retain(|_, session| !session.is_abandoned(ticket_lifetime));
+    tickets.retain(|ticket, cki_string| {
+        sessions
+            .get(cki_string)
+            .is_some_and(|session| session.has_ticket(ticket))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(ticket_lifetime: Duration) -> Session {
+        Session::new(
+            Arc::new(String::from("1")),
+            Arc::new(String::from("cki")),
+            Arc::new(String::from("alice@example.com")),
+            16,
+            ticket_lifetime,
+        )
+    }
+
+    fn add_principal(session: &Session, email: &str, mobile: bool) {
+        let email = Arc::new(email.to_string());
+        session.principals.lock().unwrap().insert(
+            email.clone(),
+            Principal {
+                email: email.clone(),
+                display_name: email,
+                client_id: None,
+                blocked: Default::default(),
+                mobile,
+            },
+        );
+    }
+
+    #[test]
+    fn unused_session_is_abandoned_after_ticket_lifetime() {
+        assert!(!session(Duration::from_secs(60)).is_abandoned(Duration::from_secs(60)));
+        assert!(session(Duration::ZERO).is_abandoned(Duration::ZERO));
+    }
+
+    #[test]
+    fn session_with_principals_is_not_abandoned() {
+        let session = session(Duration::ZERO);
+        add_principal(&session, "alice@example.com", false);
+        assert!(!session.is_abandoned(Duration::ZERO));
+    }
+
+    #[test]
+    fn session_with_only_mobile_principals_is_abandoned() {
+        let session = session(Duration::ZERO);
+        add_principal(&session, "bob@example.com", true);
+        assert!(session.is_abandoned(Duration::ZERO));
+    }
+
+    #[test]
+    fn ticket_is_redeemed_once_by_its_email() {
+        let session = session(Duration::from_secs(60));
+        let ticket = session
+            .issue_ticket(
+                Arc::new(String::from("bob@example.com")),
+                Duration::from_secs(60),
+            )
+            .unwrap();
+
+        assert!(!session.accepts_ticket(&ticket, "mallory@example.com"));
+        assert!(session.accepts_ticket(&ticket, "bob@example.com"));
+        assert!(!session.redeem_ticket(&ticket, "mallory@example.com"));
+        assert!(session.redeem_ticket(&ticket, "Bob@Example.com"));
+        assert!(!session.accepts_ticket(&ticket, "bob@example.com"));
+        assert!(!session.redeem_ticket(&ticket, "bob@example.com"));
+    }
+
+    #[test]
+    fn expired_ticket_is_refused() {
+        let session = session(Duration::from_secs(60));
+        let ticket = session
+            .issue_ticket(Arc::new(String::from("bob@example.com")), Duration::ZERO)
+            .unwrap();
+
+        assert!(!session.has_ticket(&ticket));
+        assert!(!session.accepts_ticket(&ticket, "bob@example.com"));
+        assert!(!session.redeem_ticket(&ticket, "bob@example.com"));
+    }
+
+    #[test]
+    fn tickets_are_found_through_the_index_until_removed() {
+        let session = session(Duration::from_secs(60));
+        let ticket = session
+            .issue_ticket(
+                Arc::new(String::from("bob@example.com")),
+                Duration::from_secs(60),
+            )
+            .unwrap();
+
+        let mut sessions = HashMap::from([(session.cki_string.clone(), session.clone())]);
+        let mut tickets = HashMap::from([(ticket.clone(), session.cki_string.clone())]);
+        assert!(find(&sessions, &tickets, &ticket).is_some());
+
+        remove(&mut sessions, &mut tickets, &session.cki_string);
+        assert!(tickets.is_empty());
+        assert!(find(&sessions, &tickets, &ticket).is_none());
+    }
 }
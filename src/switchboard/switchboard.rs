@@ -1,5 +1,12 @@
+use crate::config::Config;
 use crate::errors::command_error::CommandError;
 use crate::errors::server_error::ServerError;
+use crate::limits::{
+    connection_limiter::ConnectionLimiter,
+    session_limiter::{SessionGuard, SessionLimiter},
+    token_bucket::TokenBucket,
+};
+use crate::proxy_protocol;
 use crate::receive_split::receive_split;
 use crate::switchboard::commands::bye;
 use crate::switchboard::handlers::handle_authentication_command::handle_authentication_command;
@@ -9,12 +16,16 @@ use crate::{
     switchboard::session::Session,
 };
 use core::str;
-use log::{error, trace};
+use log::{error, info, trace, warn};
 use std::error;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::{
     io::AsyncWriteExt,
     net::{TcpStream, tcp::WriteHalf},
     sync::broadcast::{self},
+    time::{Instant, sleep_until, timeout_at},
 };
 
 pub struct Switchboard {
@@ -23,16 +34,78 @@ pub struct Switchboard {
     session_rx: Option<broadcast::Receiver<Message>>,
     authenticated_user: Option<AuthenticatedUser>,
     protocol_version: Option<u32>,
+    config: Arc<Config>,
+    peer_address: SocketAddr,
+    connected_at: Instant,
+    rate_limiter: TokenBucket,
+    session_limiter: SessionLimiter,
+    session_guard: Option<SessionGuard>,
+    last_activity: Instant,
+}
+
+/// Reads the PROXY protocol header if enabled, then serves the connection until it closes
+pub async fn serve(
+    mut socket: TcpStream,
+    accepted_address: SocketAddr,
+    broadcast_tx: broadcast::Sender<Message>,
+    config: Arc<Config>,
+    connection_limiter: ConnectionLimiter,
+    session_limiter: SessionLimiter,
+) {
+    let peer_address = match proxy_protocol::peer_address(
+        &mut socket,
+        accepted_address,
+        config.switchboard.proxy_protocol,
+    )
+    .await
+    {
+        Ok(peer_address) => peer_address,
+        Err(error) => {
+            warn!("Rejected Switchboard connection from {accepted_address}: {error}");
+            return;
+        }
+    };
+
+    let Some(_connection_guard) = connection_limiter.acquire(peer_address.ip()) else {
+        warn!("Rejected Switchboard connection from {peer_address}: too many connections");
+        return;
+    };
+
+    info!("Switchboard connection from {peer_address}");
+    let mut connection = Switchboard::new(broadcast_tx, config, session_limiter, peer_address);
+    loop {
+        if let Err(error) = connection.listen(&mut socket).await {
+            error!("{peer_address}: {error}");
+            break;
+        }
+    }
 }
 
 impl Switchboard {
-    pub fn new(broadcast_tx: broadcast::Sender<Message>) -> Self {
+    pub fn new(
+        broadcast_tx: broadcast::Sender<Message>,
+        config: Arc<Config>,
+        session_limiter: SessionLimiter,
+        peer_address: SocketAddr,
+    ) -> Self {
+        let rate_limiter = TokenBucket::new(
+            config.limits.command_burst,
+            config.limits.commands_per_second,
+        );
+
         Switchboard {
             broadcast_tx: broadcast_tx.clone(),
             session: None,
             session_rx: None,
             authenticated_user: None,
             protocol_version: None,
+            config,
+            peer_address,
+            connected_at: Instant::now(),
+            rate_limiter,
+            session_limiter,
+            session_guard: None,
+            last_activity: Instant::now(),
         }
     }
 
@@ -47,24 +120,47 @@ impl Switchboard {
                 .as_mut()
                 .ok_or(ServerError::CouldNotGetSessionReceiver)?;
 
+            let idle_timeout = self.config.switchboard.idle_timeout_seconds;
+            let idle_deadline = self.last_activity + Duration::from_secs(idle_timeout);
+
             tokio::select! {
                 messages = receive_split(&mut rd) => {
-                    if let Err(error) = self.handle_client_commands(&mut wr, messages?).await {
-                        if let Some(session) = self.session.as_ref() {
-                            self.broadcast_tx.send(Message::RemoveSession(session.session_id.clone()))?;
-                            self.send_bye_to_principals(false).await?;
-                        }
+                    let result = match messages {
+                        Ok(messages) => self.handle_client_commands(&mut wr, messages).await,
+                        Err(error) => Err(error.into()),
+                    };
 
+                    if let Err(error) = result {
+                        self.send_bye_to_principals(false).await?;
                         return Err(error);
                     }
                 }
 
                 received = session_rx.recv() => {
-                    self.handle_session_message(&mut wr, received.map_err(CommandError::CouldNotReceiveFromBroadcast)?).await?
+                    let message = received.map_err(CommandError::CouldNotReceiveFromBroadcast)?;
+                    if let Message::EndSessions(email) = &message
+                        && self.authenticated_user.as_ref().is_some_and(|user| user.email == *email)
+                    {
+                        self.send_bye_to_principals(false).await?;
+                        return Err(ServerError::AccountDeleted.into());
+                    }
+
+                    self.handle_session_message(&mut wr, message).await?
+                }
+
+                _ = sleep_until(idle_deadline), if idle_timeout != 0 => {
+                    self.send_bye_to_principals(true).await?;
+                    return Err(ServerError::SessionIdle.into());
                 }
             }
         } else {
-            let messages = receive_split(&mut rd).await?;
+            let deadline = self.connected_at
+                + Duration::from_secs(self.config.limits.authentication_timeout_seconds);
+
+            let messages = timeout_at(deadline, receive_split(&mut rd))
+                .await
+                .or(Err(ServerError::AuthenticationTimeout))??;
+
             self.handle_client_commands(&mut wr, messages).await?;
         }
 
@@ -77,16 +173,36 @@ impl Switchboard {
         messages: Vec<Vec<u8>>,
     ) -> Result<(), Box<dyn error::Error + Send + Sync>> {
         for message in messages {
+            if !self.rate_limiter.try_take() {
+                wr.write_all(b"OUT\r\n").await?;
+                warn!("S: OUT\r\n");
+                return Err(ServerError::RateLimited.into());
+            }
+
             if self.session.is_none() {
-                let Some((protocol_version, session, authenticated_user)) =
-                    handle_authentication_command(&self.broadcast_tx, wr, message).await?
+                let Some((protocol_version, session, authenticated_user, session_guard)) =
+                    handle_authentication_command(
+                        &self.broadcast_tx,
+                        &self.config,
+                        &self.session_limiter,
+                        wr,
+                        message,
+                    )
+                    .await?
                 else {
                     continue;
                 };
 
+                info!(
+                    "{} joined a switchboard session from {}",
+                    authenticated_user.email, self.peer_address
+                );
+
                 self.protocol_version = Some(protocol_version);
                 self.authenticated_user = Some(authenticated_user);
                 self.session = Some(session);
+                self.session_guard = Some(session_guard);
+                self.last_activity = Instant::now();
 
                 if let Some(session) = &self.session {
                     self.session_rx = Some(session.session_tx.subscribe());
@@ -95,6 +211,7 @@ impl Switchboard {
                 continue;
             }
 
+            self.last_activity = Instant::now();
             handle_session_command(
                 self.protocol_version
                     .ok_or(ServerError::CouldNotGetProtocolVersion)?,
@@ -105,6 +222,7 @@ impl Switchboard {
                     .as_mut()
                     .ok_or(ServerError::CouldNotGetSession)?,
                 &self.broadcast_tx,
+                &self.config,
                 wr,
                 message,
             )
@@ -137,21 +255,33 @@ impl Switchboard {
             return Ok(());
         };
 
-        if *principal
-            == *self
-                .authenticated_user
-                .as_ref()
-                .ok_or(CommandError::CouldNotGetAuthenticatedUser)?
-                .email
-        {
+        let authenticated_user = self
+            .authenticated_user
+            .as_ref()
+            .ok_or(CommandError::CouldNotGetAuthenticatedUser)?;
+
+        if *principal == *authenticated_user.email {
             return Ok(());
         }
 
+        let blocked = self
+            .session
+            .as_ref()
+            .is_some_and(|session| session.blocks(&authenticated_user.email, &sender));
+
         trace!("Thread {sender}: {command}");
         match *args.first().unwrap_or(&"") {
+            // Only the block list counts here, with BLP set to BL joining the session is what allows the others.
+            // The sender still gets their ACK, like when a message is dropped on the way
+            "MSG" if blocked => {
+                trace!("Dropped message from {sender}, who is blocked");
+                self.last_activity = Instant::now();
+            }
+
             "MSG" => {
                 wr.write_all(&message).await?;
                 trace!("S: {command}");
+                self.last_activity = Instant::now();
             }
 
             "JOI" => {
@@ -184,6 +314,7 @@ impl Switchboard {
             .authenticated_user
             .as_mut()
             .ok_or(ServerError::CouldNotGetAuthenticatedUser)?;
+        let abandoned;
         {
             let mut principals = self
                 .session
@@ -194,6 +325,7 @@ impl Switchboard {
                 .or(Err(ServerError::PrincipalsLockError))?;
 
             principals.remove(&authenticated_user.email);
+            abandoned = principals.values().all(|principal| principal.mobile);
         }
 
         let mut bye_command = bye::generate(
@@ -214,6 +346,10 @@ impl Switchboard {
 
         if let Some(ref session) = self.session {
             session.session_tx.send(message)?;
+            if abandoned {
+                self.broadcast_tx
+                    .send(Message::RemoveSession(session.cki_string.clone()))?;
+            }
         }
 
         Ok(())
@@ -0,0 +1,72 @@
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use log::{error, info};
+use sha2::Sha256;
+use sqlx::{MySql, Pool};
+use std::time::Duration;
+
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Keyed hash of a bearer token or ticket, the only form in which tokens are stored
+pub fn hash(secret: &str, token: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+
+    mac.update(token.as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Deletes expired tokens, password reset links, email confirmations and two-factor challenges every hour
+pub async fn purge_expired(pool: Pool<MySql>) {
+    let mut interval = tokio::time::interval(PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(error) = sqlx::query!(
+            "DELETE FROM password_resets WHERE valid_until < ?",
+            Utc::now().naive_utc()
+        )
+        .execute(&pool)
+        .await
+        {
+            error!("Could not purge expired password resets: {error}");
+        }
+
+        if let Err(error) = sqlx::query!(
+            "DELETE FROM email_confirmations WHERE valid_until < ?",
+            Utc::now().naive_utc()
+        )
+        .execute(&pool)
+        .await
+        {
+            error!("Could not purge expired email confirmations: {error}");
+        }
+
+        if let Err(error) = sqlx::query!(
+            "DELETE FROM two_factor_challenges WHERE valid_until < ?",
+            Utc::now().naive_utc()
+        )
+        .execute(&pool)
+        .await
+        {
+            error!("Could not purge expired two-factor challenges: {error}");
+        }
+
+        match sqlx::query!(
+            "DELETE FROM tokens WHERE valid_until < ?",
+            Utc::now().naive_utc()
+        )
+        .execute(&pool)
+        .await
+        {
+            Ok(result) if result.rows_affected() > 0 => {
+                info!("Purged {} expired tokens", result.rows_affected());
+            }
+            Ok(_) => (),
+            Err(error) => error!("Could not purge expired tokens: {error}"),
+        }
+    }
+}